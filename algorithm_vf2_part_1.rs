@@ -1,322 +1,285 @@
-use std::fmt::write;
-use std::fs;
-use std::io;
-use std::collections::{HashMap, HashSet};
-use std::thread;
-use std::time::Duration;
-use std::hash::{Hash, Hasher};
-use csv::WriterBuilder;
-use std::cmp::Eq;
-use std::fs::File;
-use std::f64::INFINITY;
-use std::ops::Deref;
-use std::f64::consts::PI;
-
-// Implementierung von Eq und Hash für die Struktur Line
-impl Eq for Line {}
-
-impl Hash for Line {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        // Felder die für die Hashberechnung nötig sind
-        self.schedule_id.hash(state);
-        self.datum.hash(state);
-    }
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+use rayon::prelude::*;
+
+use crate::config::Tolerances;
+use crate::heuristic_cost::HeuristicCost;
+use crate::matching_params::MatchingParams;
+use crate::delay_model::DelayTracker;
+use crate::event_index::{haversine_m, EventIndex};
+use crate::trip_score::{normalized_error, TripMatch, TripScore};
+use crate::model::{ScheduleStop, VehicleEvent};
+
+// Struktur, um eine Kante im Graphen darzustellen
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub scheduled_seconds: i64, // Geplante Fahrzeit zwischen zwei aufeinanderfolgenden Haltestellen
 }
 
-impl<'a> Eq for &'a Bus {}
+// Gerichteter Graph einer Fahrt: die Knoten sind die Haltestellen in der Reihenfolge von LFD_NR,
+// die Kanten verbinden jeweils zwei aufeinanderfolgende Haltestellen
+#[derive(Debug)]
+pub struct TripGraph<'a> {
+    pub key: &'a str,
+    pub nodes: Vec<&'a ScheduleStop>,
+    pub edges: Vec<Edge>,
+}
 
-impl<'a> Hash for &'a Bus {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.vehicle.hash(state);
+impl<'a> TripGraph<'a> {
+    pub fn new(key: &'a str, stops: &'a [ScheduleStop]) -> Self {
+        let mut nodes: Vec<&ScheduleStop> = stops.iter().collect();
+        nodes.sort_by_key(|stop| (stop.lfnr, stop.zeit));
+        let edges = nodes.windows(2).map(|pair| Edge {
+            scheduled_seconds: pair[1].zeit as i64 - pair[0].zeit as i64,
+        }).collect();
+        TripGraph { key, nodes, edges }
     }
-}
-use csv::ReaderBuilder;
-// Struktur für eine Linie
-#[derive(Debug, PartialEq)]
-pub struct Line {
-    schedule_id: String,
-    datum: String,
-    frt_fid: u32,
-    frt_start: u32,
-    line: u32,
-    richtung: u32,
-    varianten: u32,
-    umlauf: u32,
-    lfnr: u32,
-    ankunft: u32,
-    abfahrt: u32,
-    zeitpkt: String,
-    zeit: u64,
-    ort_nr: u32,
-    ort_name: String,
-    lon: f64,
-    lat: f64,
-    x: f64,
-    y: f64,
-    fahrt_start: String,
-    fahrt_ende: String,
-    wkt: String,
-}
 
-// Struktur für einen Bus
-#[derive(Debug, PartialEq)]
-pub struct Bus {
-    vehicle: String,
-    datum: String,
-    zeit: String,
-    zeit_next: String,
-    unixzeit: u64,
-    lat: f64,
-    lon: f64,
-    x: f64,
-    y: f64,
-    typ: String,
-    einsteiger: u32,
-    aussteiger: u32,
-    wkt: String,
+    // Geplante Fahrzeit zwischen zwei Haltestellen entlang der Kanten (auch über ausgelassene Haltestellen hinweg)
+    fn scheduled_seconds(&self, from: usize, to: usize) -> i64 {
+        self.edges[from..to].iter().map(|edge| edge.scheduled_seconds).sum()
+    }
 }
 
-// Struktur, um eine Kante im Graphen darzustellen
+// Gerichteter Graph eines Fahrzeugtages: die Knoten sind die Ereignisse in zeitlicher Reihenfolge,
+// von jedem Ereignis führt eine Kante zu jedem späteren (die Kanten ergeben sich aus der Reihenfolge)
 #[derive(Debug)]
-pub struct Edge {
-    edge_nummer: u64,
-    edge_zuordnung: Vec<(String, String)>, // Liste von Haltestellen-Tupeln (Quelle, Ziel)
-}
-
-#[derive(Clone)]
-pub struct VF2State<'a> {
-    lines_graph: &'a HashMap<String, Vec<Line>>,
-    buses_graph: &'a HashMap<String, Vec<Bus>>,
-    mapping: HashMap<&'a Line, &'a Bus>,
-    partial_match: HashMap<(&'a Line, &'a Bus), bool>,
+pub struct VehicleGraph<'a> {
+    pub vehicle: &'a str,
+    pub nodes: Vec<&'a VehicleEvent>,
 }
 
-pub fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    const R: f64 = 6371.0; // Radius der Erde in Kilometern
-
-    let d_lat = (lat2 - lat1).to_radians();
-    let d_lon = (lon2 - lon1).to_radians();
+impl<'a> VehicleGraph<'a> {
+    pub fn new(vehicle: &'a str, events: &'a [VehicleEvent]) -> Self {
+        let mut nodes: Vec<&VehicleEvent> = events.iter().collect();
+        nodes.sort_by_key(|event| event.unixzeit);
+        VehicleGraph { vehicle, nodes }
+    }
 
-    let a = (d_lat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
-    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    // Indizes der Ereignisse im Zeitfenster [from, to]
+    fn events_between(&self, from: u64, to: u64) -> Range<usize> {
+        let start = self.nodes.partition_point(|event| event.unixzeit < from);
+        let end = self.nodes.partition_point(|event| event.unixzeit <= to);
+        start..end.max(start)
+    }
+}
 
-    R * c
+// Zustand der VF2-Suche für ein Paar aus Fahrtgraph und Fahrzeuggraph.
+// `mapping` enthält für die bereits betrachteten Haltestellen das zugeordnete Ereignis (None = ausgelassen),
+// `partial_match` merkt sich, ob ein Paar (Haltestelle, Ereignis) die Bedingungen an Zeit und Ort erfüllt.
+pub struct VF2State<'a> {
+    lines_graph: &'a TripGraph<'a>,
+    buses_graph: &'a VehicleGraph<'a>,
+    tolerances: &'a Tolerances,
+    cost: &'a HeuristicCost, // Bewertung des Fahrzeugs über die Fahrt (TripScore)
+    mapping: Vec<Option<usize>>,
+    partial_match: HashMap<(usize, usize), bool>,
+    matched: usize,
+    states: usize,
+    best: Option<(Vec<Option<usize>>, usize, f64)>,
 }
 
-pub fn is_time_compatible(line_time: u64, bus_time: u64) -> bool {
-    // Konvertiere line_time von u32 nach u64
-    let line_time_u64 = line_time as u64;
+impl<'a> VF2State<'a> {
+    pub fn new(lines_graph: &'a TripGraph<'a>, buses_graph: &'a VehicleGraph<'a>, tolerances: &'a Tolerances, cost: &'a HeuristicCost) -> Self {
+        VF2State {
+            lines_graph,
+            buses_graph,
+            tolerances,
+            cost,
+            mapping: Vec::new(),
+            partial_match: HashMap::new(),
+            matched: 0,
+            states: 0,
+            best: None,
+        }
+    }
 
-    // Definiere die maximale Verspätung oder Verfrühung in Sekunden (hier 10 Minuten)
-    let time_tolerance_seconds = 10 * 60;
+    // Führt die Suche aus und liefert die beste gefundene Zuordnung
+    pub fn run(mut self) -> Option<TripMatch> {
+        self.search(0.0);
+        let (mapping, _, _) = self.best?;
+        let stops: Vec<(ScheduleStop, VehicleEvent)> = mapping.iter().enumerate()
+            .filter_map(|(stop, event)| event.map(|event| (self.lines_graph.nodes[stop].clone(), self.buses_graph.nodes[event].clone())))
+            .collect();
+        Some(TripMatch {
+            trip_key: self.lines_graph.key.to_string(),
+            vehicle: self.buses_graph.vehicle.to_string(),
+            score: TripScore::from_pairs(self.lines_graph.nodes.len(), &stops, self.cost, self.tolerances),
+            stops,
+        })
+    }
+
+    fn skipped(&self) -> usize {
+        self.mapping.len() - self.matched
+    }
 
-    // Berechne die Differenz zwischen den Zeitstempeln
-    let time_difference = (line_time_u64 as i64 - bus_time as i64).abs();
+    // Zuletzt zugeordnete Haltestelle und ihr Ereignis
+    fn last_pair(&self) -> Option<(usize, usize)> {
+        self.mapping.iter().enumerate().rev().find_map(|(stop, event)| event.map(|event| (stop, event)))
+    }
 
-    // Überprüfe, ob die Differenz innerhalb der Toleranz liegt
-    //print!("Zeit:{:?}", time_difference);
+    // Rekursive Tiefensuche über die Zustände: in jeder Tiefe wird die nächste Haltestelle einem Ereignis zugeordnet
+    // oder ausgelassen. Gemerkt wird die Zuordnung mit den meisten Paaren und bei Gleichstand den geringsten Kosten.
+    fn search(&mut self, cost: f64) {
+        self.states += 1;
+        if self.states > self.tolerances.max_search_states {
+            return;
+        }
 
-    time_difference <= time_tolerance_seconds
-}
+        let depth = self.mapping.len();
+        let stops = self.lines_graph.nodes.len();
 
-// Funktion zum Zuordnen von Linien zu Bussen basierend auf den nächsten und zeitlich passenden Koordinaten
-pub fn assign_buses_to_lines(lines: &HashMap<String, Vec<Line>>, buses: &HashMap<String, Vec<Bus>>) -> HashMap<String, String> {
-    let mut assignment: HashMap<String, String> = HashMap::new();
-    let mut i = 0;
-    for (line_id, line_vec) in lines.iter() {
-        i+=1;
-        //println!("Linie: {:?}, {:?}", i, line_id);
-        for line in line_vec {
-            let mut min_distance = 1000.0;
-            let mut closest_bus: Option<&Bus> = None;
-
-            for (bus_id, bus_vec) in buses {
-                let mut min_stop_distance = 1000.0;
-                for bus_zustand in bus_vec {
-                    
-                    let distance = calculate_distance(line.lat, line.lon, bus_zustand.lat, bus_zustand.lon);
-                    
-                    if distance < min_distance && is_time_compatible(line.zeit, bus_zustand.unixzeit) {
-                        min_distance = min_stop_distance;
-                        //print!("Zeit und Distanz: {:?}:{:?}", line.ankunft, bus_zustand.unixzeit);
-                        closest_bus = Some(bus_zustand);
-                    }
-                }
-                
+        // Zustände, die die beste Lösung nicht mehr übertreffen können, werden verworfen
+        if let Some((_, best_matched, best_cost)) = &self.best {
+            let potential = self.matched + (stops - depth);
+            if potential < *best_matched || (potential == *best_matched && cost >= *best_cost) {
+                return;
             }
-            
+        }
 
-            if let Some(bus) = closest_bus {
-                //println!("{:?}:{:?}", line_id.clone(), bus.vehicle.clone());
-                assignment.insert(line_id.clone(), bus.vehicle.clone());
+        if depth == stops {
+            if self.matched > 0 {
+                self.best = Some((self.mapping.clone(), self.matched, cost));
             }
+            return;
         }
-    }
 
-    assignment
-}
+        let last = self.last_pair();
+        for (event, pair_cost) in self.candidate_pairs(depth, last) {
+            if self.is_feasible(depth, event, last) {
+                self.mapping.push(Some(event));
+                self.matched += 1;
+                self.search(cost + pair_cost);
+                self.matched -= 1;
+                self.mapping.pop();
+            }
+        }
 
-pub fn run(line_folder_path: &str, bus_folder_path: &str, output_file: &str) -> io::Result<()> {
-    // Einlesen der Linien- und Busdaten aus den CSV-Dateien
-    let lines = read_lines_from_csv_folder(line_folder_path)?;
-    let buses = read_buses_from_csv_folder(bus_folder_path)?;
-    println!("Eingelesen der Busse und Linien");
-    // Zuordnen von Linien zu Bussen basierend auf den nächsten und zeitlich passenden Koordinaten
-    let assignment = assign_buses_to_lines(&lines, &buses);
-    let output_datei_erstellen = write_assignment_to_csv(&assignment, output_file);
-    print!("Erstellt der Zuordnung");
-    // Ausgabe der zugeordneten Linien und Busse
-    for (line_id, bus_id) in assignment {
-        println!("Linien-ID: {}, Bus-ID: {}", line_id, bus_id);
+        if self.skipped() < self.tolerances.max_skipped_stops {
+            self.mapping.push(None);
+            self.search(cost);
+            self.mapping.pop();
+        }
     }
-    Ok(())
-}
 
-// Funktion zum Speichern der Zuordnung in eine CSV-Datei
-pub fn save_assignment_to_csv(assignment: &HashMap<&Line, &Bus>, output_file: &str) -> io::Result<()> {
-    // Erstellen des CSV-Writers
-    let file = fs::File::create(output_file)?;
-    let mut writer = WriterBuilder::new().delimiter(b';').from_writer(file);
-
-    // Schreiben der Header-Zeile
-    writer.write_record(&["Line_Schedule_ID", "Bus_Vehicle"])?;
-
-    // Schreiben der Zuordnungen
-    for (line, bus) in assignment {
-        writer.serialize((&line.schedule_id, &bus.vehicle))?;
+    // Erwartete Verspätung nach dem zuletzt zugeordneten Paar
+    fn delay_after(&self, last: Option<(usize, usize)>) -> DelayTracker<'a> {
+        let last_times = last.map(|(stop, event)| (self.lines_graph.nodes[stop].zeit, self.buses_graph.nodes[event].unixzeit));
+        DelayTracker::after(self.tolerances, last_times)
     }
 
-    // Abschließen des Schreibvorgangs
-    writer.flush()?;
-    Ok(())
-}
-
-pub fn write_assignment_to_csv(assignment: &HashMap<String, String>, output_file: &str) -> io::Result<()> {
-    let mut writer = csv::Writer::from_writer(File::create(output_file)?);
-
-    // Schreibe die Überschrift
-    writer.write_record(&["Line_ID", "Bus_ID"])?;
-
-    // Schreibe die Zuordnungen
-    for (line_id, bus_id) in assignment {
-        writer.write_record(&[line_id, bus_id])?;
+    // Kandidatenpaare für eine Haltestelle: alle Ereignisse im Zeitfenster nach dem zuletzt zugeordneten Ereignis,
+    // aufsteigend nach ihren Kosten sortiert, damit gute Lösungen früh gefunden werden
+    fn candidate_pairs(&self, stop: usize, last: Option<(usize, usize)>) -> Vec<(usize, f64)> {
+        let (from, to) = self.delay_after(last).window(self.lines_graph.nodes[stop].zeit);
+        let window = self.buses_graph.events_between(from, to);
+        let start = last.map_or(window.start, |(_, event)| window.start.max(event + 1));
+
+        let mut candidates: Vec<(usize, f64)> = (start..window.end.max(start))
+            .map(|event| (event, self.pair_cost(stop, event)))
+            .collect();
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        candidates
     }
 
-    writer.flush()?;
-    Ok(())
-}
+    // Normierte Abweichung eines Paares in Ort und Zeit
+    fn pair_cost(&self, stop: usize, event: usize) -> f64 {
+        let stop = self.lines_graph.nodes[stop];
+        let event = self.buses_graph.nodes[event];
+        let distance_m = haversine_m(stop.lat, stop.lon, event.lat, event.lon);
+        let time_difference = (stop.zeit as i64 - event.unixzeit as i64).unsigned_abs();
+        normalized_error(distance_m, time_difference, self.tolerances)
+    }
 
-// Funktion zum Einlesen von Linieninformationen aus CSV-Dateien und Erstellen eines Graphen
-pub fn read_lines_from_csv_folder(folder_path: &str) -> io::Result<HashMap<String, Vec<Line>>> {
-    let mut lines_graph: HashMap<String, Vec<Line>> = HashMap::new();
-    for entry in fs::read_dir(folder_path)? {
-        let entry = entry?;
-        let file_path = entry.path();
-        if file_path.is_file() && file_path.extension().unwrap_or_default() == "csv" {
-            let mut lines = Vec::new();
-            let file_stem = file_path.file_stem().unwrap().to_string_lossy().to_string();
-            let mut rdr = ReaderBuilder::new().delimiter(b';').from_path(&file_path)?;
-            for result in rdr.records() {
-                let record = result?;
-                let line = Line {
-                    schedule_id: record[0].to_string(),
-                    datum: record[1].to_string(),
-                    frt_fid: record[2].parse().unwrap(),
-                    frt_start: record[3].parse().unwrap(),
-                    line: record[4].parse().unwrap(),
-                    richtung: record[5].parse().unwrap(),
-                    varianten: record[6].parse().unwrap(),
-                    umlauf: record[7].parse().unwrap(),
-                    lfnr: record[8].parse().unwrap(),
-                    ankunft: record[9].parse().unwrap(),
-                    abfahrt: record[10].parse().unwrap(),
-                    zeitpkt: record[11].to_string(),
-                    zeit: record[12].parse().unwrap(),
-                    ort_nr: record[13].parse().unwrap(),
-                    ort_name: record[14].to_string(),
-                    lon: record[15].parse().unwrap(),
-                    lat: record[16].parse().unwrap(),
-                    x: record[17].parse().unwrap(),
-                    y: record[18].parse().unwrap(),
-                    fahrt_start: record[19].to_string(),
-                    fahrt_ende: record[20].to_string(),
-                    wkt: record[21].to_string(),
-                };
-                lines.push(line);
+    // Prüft, ob das Paar (Haltestelle, Ereignis) den aktuellen Zustand erweitern darf
+    fn is_feasible(&mut self, stop: usize, event: usize, last: Option<(usize, usize)>) -> bool {
+        // Reihenfolge: das Ereignis liegt nach dem Ereignis der vorherigen Haltestelle, und die gefahrene Zeit
+        // weicht höchstens um max_delay_seconds von der geplanten Fahrzeit entlang der Kanten ab
+        if let Some((last_stop, last_event)) = last {
+            if event <= last_event {
+                return false;
+            }
+            let actual = self.buses_graph.nodes[event].unixzeit as i64 - self.buses_graph.nodes[last_event].unixzeit as i64;
+            let scheduled = self.lines_graph.scheduled_seconds(last_stop, stop);
+            if !is_edge_compatible(scheduled, actual, self.tolerances.max_delay_seconds) {
+                return false;
             }
-            lines_graph.entry(file_stem).or_insert(Vec::new()).extend(lines);
         }
-    }
-    Ok(lines_graph)
-}
 
-pub fn read_buses_from_csv_folder(folder_path: &str) -> io::Result<HashMap<String, Vec<Bus>>> {
-    let mut buses_graph: HashMap<String, Vec<Bus>> = HashMap::new();
-    
-    // Durchlaufe alle Dateien im angegebenen Ordner
-    for entry in fs::read_dir(folder_path)? {
-        let entry = entry?;
-        let file_path = entry.path();
-        
-        // Prüfe, ob die Datei eine CSV-Datei ist
-        if file_path.is_file() && file_path.extension().unwrap_or_default() == "csv" {
-            let mut buses = Vec::new();
-            let file_stem = file_path.file_stem().unwrap().to_string_lossy().to_string();
-            
-            // Öffne die CSV-Datei und lese die Zeilen ein
-            let mut rdr = ReaderBuilder::new().delimiter(b';').from_path(&file_path)?;
-            for result in rdr.records().skip(1) { // Überspringe Header-Zeile
-                let record = result?;
-                
-                // Erstelle ein Bus-Objekt aus den Daten der Zeile und füge es zum Vektor hinzu
-                let bus = Bus {
-                    vehicle: record[0].to_string(),
-                    datum: record[1].to_string(),
-                    zeit: record[2].to_string(),
-                    zeit_next: record[3].to_string(),
-                    unixzeit: record[4].parse().unwrap(),
-                    lat: record[5].parse().unwrap(),
-                    lon: record[6].parse().unwrap(),
-                    x: record[7].parse().unwrap(),
-                    y: record[8].parse().unwrap(),
-                    typ: record[9].to_string(),
-                    einsteiger: record[10].parse().unwrap(),
-                    aussteiger: record[11].parse().unwrap(),
-                    wkt: record[12].to_string(),
-                };
-                buses.push(bus);
-            }
-            
-            // Füge den Vektor zum Graphen hinzu, wobei der Dateiname als Schlüssel verwendet wird
-            buses_graph.entry(file_stem).or_insert(Vec::new()).extend(buses);
+        // Vorausschau: für die restlichen Haltestellen müssen abzüglich der erlaubten Auslassungen genug spätere Ereignisse bleiben
+        let remaining_stops = self.lines_graph.nodes.len() - stop - 1;
+        let remaining_skips = self.tolerances.max_skipped_stops.saturating_sub(self.skipped());
+        let remaining_events = self.buses_graph.nodes.len() - event - 1;
+        if remaining_stops.saturating_sub(remaining_skips) > remaining_events {
+            return false;
+        }
+
+        // Die Zeit hängt über die erwartete Verspätung vom vorherigen Paar ab
+        if !is_time_compatible(self.lines_graph.nodes[stop].zeit, self.buses_graph.nodes[event].unixzeit, &self.delay_after(last)) {
+            return false;
         }
+
+        // Der Ort hängt nicht vom Zustand ab und wird deshalb zwischengespeichert
+        let (lines_graph, buses_graph, tolerances) = (self.lines_graph, self.buses_graph, self.tolerances);
+        *self.partial_match.entry((stop, event)).or_insert_with(|| {
+            let stop = lines_graph.nodes[stop];
+            let event = buses_graph.nodes[event];
+            haversine_m(stop.lat, stop.lon, event.lat, event.lon) <= tolerances.match_distance_m
+        })
     }
-    
-    Ok(buses_graph)
 }
 
-pub fn print_lines(lines_graph: &HashMap<String, Vec<Line>>) {
-    for (file_name, lines) in lines_graph.iter() {
-        println!("Linien aus der Datei {}: ", file_name);
-        thread::sleep(Duration::from_secs(1));
-        /*for (index, line) in buses.iter().enumerate() {
-            println!("Bus {}:", index + 1);
-            println!("{:?}", bus);
-            thread::sleep(Duration::from_secs(1));
-        }*/
-    }
+pub fn is_time_compatible(line_time: u64, bus_time: u64, delay: &DelayTracker) -> bool {
+    // Das Ereignis darf höchstens early_tolerance_seconds vor und time_tolerance_seconds nach der erwarteten Zeit liegen
+    // (Standard 3 bzw. 10 Minuten); die erwartete Zeit folgt der Verspätung an der vorherigen Haltestelle
+    delay.accepts(line_time, bus_time)
 }
 
-pub fn print_buses(buses_graph: &HashMap<String, Vec<Bus>>) {
-    for (file_name, buses) in buses_graph.iter() {
-        println!("Busse aus der Datei {}: ", file_name);
-        thread::sleep(Duration::from_secs(1));
+// Prüft, ob die gefahrene Zeit zwischen zwei Ereignissen zur geplanten Fahrzeit passt
+pub fn is_edge_compatible(scheduled_seconds: i64, actual_seconds: i64, max_delay_seconds: u64) -> bool {
+    actual_seconds >= 0 && (actual_seconds - scheduled_seconds).abs() <= max_delay_seconds as i64
+}
 
-        /*for (index, bus) in buses.iter().enumerate() {
-            println!("Bus {}:", index + 1);
-            println!("{:?}", bus);
-            thread::sleep(Duration::from_secs(1));
-        }*/
+// Sucht die Zuordnung einer Fahrt zu einem bestimmten Fahrzeug
+pub fn match_trip_to_vehicle(trip_key: &str, stops: &[ScheduleStop], vehicle: &str, events: &[VehicleEvent], cost: &HeuristicCost, tolerances: &Tolerances) -> Option<TripMatch> {
+    let trip = TripGraph::new(trip_key, stops);
+    let bus = VehicleGraph::new(vehicle, events);
+    VF2State::new(&trip, &bus, tolerances, cost).run()
+}
 
-    }
-}
\ No newline at end of file
+// Sucht für jede Fahrt alle Fahrzeuge, deren Ereignisgraph die Fahrt enthält, sortiert von der besten zur schlechtesten
+// Bewertung (meiste Haltestellen, geringste Abweichungen). Über den Index werden nur Fahrzeuge betrachtet, die mindestens
+// ein Ereignis in Zeit- und Entfernungstoleranz zu einer Haltestelle der Fahrt haben. Die Toleranzen gelten je Fahrt.
+pub fn match_trip_candidates(lines: &HashMap<String, Vec<ScheduleStop>>, buses: &HashMap<String, Vec<VehicleEvent>>, params: &MatchingParams, cost: &HeuristicCost) -> BTreeMap<String, Vec<TripMatch>> {
+    let buses_graphs: BTreeMap<&str, VehicleGraph> = buses.iter()
+        .map(|(bus_id, bus_vec)| (bus_id.as_str(), VehicleGraph::new(bus_id, bus_vec)))
+        .collect();
+    let index = EventIndex::new(buses.values().flatten(), params.default.match_distance_m, params.default.time_tolerance_seconds);
+
+    let mut line_ids: Vec<&String> = lines.keys().collect();
+    line_ids.sort();
+
+    // Jede Fahrt wird unabhängig und parallel gesucht; collect behält die Reihenfolge der Schlüssel bei
+    line_ids.into_par_iter().map(|line_id| {
+        let lines_graph = TripGraph::new(line_id, &lines[line_id]);
+        let tolerances = params.for_trip(&lines[line_id]);
+        let on_time = DelayTracker::new(&tolerances);
+
+        // Kandidaten: Fahrzeuge mit Ereignissen in der Nähe der Haltestellen im Zeitfenster um die Soll-Zeit
+        let candidates: BTreeSet<&str> = lines_graph.nodes.iter()
+            .flat_map(|stop| {
+                let (from, to) = on_time.window(stop.zeit);
+                index.query_window(stop.lat, stop.lon, tolerances.match_distance_m, from, to)
+            })
+            .map(|event| event.vehicle.as_str())
+            .collect();
+
+        let mut matches: Vec<TripMatch> = candidates.into_iter()
+            .filter_map(|bus_id| buses_graphs.get(bus_id))
+            .filter_map(|buses_graph| VF2State::new(&lines_graph, buses_graph, &tolerances, cost).run())
+            .collect();
+        // Stabile Sortierung: bei gleicher Bewertung bleibt die kleinere Fahrzeugkennung vorne
+        matches.sort_by(|a, b| b.score.compare(&a.score));
+        (line_id.clone(), matches)
+    }).collect()
+}
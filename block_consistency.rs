@@ -0,0 +1,143 @@
+// Umläufe: die Fahrten eines Umlaufs (gleicher Betriebstag und gleiche Umlaufnummer) werden planmäßig von einem
+// Fahrzeug gefahren. Erkennt Fahrzeugwechsel innerhalb eines Umlaufs und ordnet auf Wunsch ganze Umläufe zu.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use csv::WriterBuilder;
+use rayon::prelude::*;
+
+use crate::matcher::{MatchResult, TripMatcher};
+use crate::model::{ScheduleStop, VehicleEvent};
+use crate::trip_score::TripMatch;
+
+// Betriebstag und Umlaufnummer
+pub type BlockKey = (String, u32);
+
+// Wechsel des Fahrzeugs zwischen zwei aufeinanderfolgenden Fahrten eines Umlaufs
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSwitch {
+    pub datum: String,
+    pub umlauf: u32,
+    pub from_trip: String,
+    pub from_vehicle: String,
+    pub to_trip: String,
+    pub to_vehicle: String,
+}
+
+// Fasst die Fahrten nach Umläufen zusammen, innerhalb eines Umlaufs nach der Abfahrtszeit sortiert
+pub fn group_trips_by_block(trips: &HashMap<String, Vec<ScheduleStop>>) -> BTreeMap<BlockKey, Vec<&String>> {
+    let mut blocks: BTreeMap<BlockKey, Vec<(u64, &String)>> = BTreeMap::new();
+    for (trip_key, stops) in trips {
+        if let Some(first) = stops.first() {
+            // Haltestellen ohne Soll-Zeit (ZEIT = 0) zählen nicht für die Abfahrtszeit
+            let start = stops.iter().map(|stop| stop.zeit).filter(|zeit| *zeit > 0).min().unwrap_or(0);
+            blocks.entry((first.datum.clone(), first.umlauf)).or_default().push((start, trip_key));
+        }
+    }
+    blocks.into_iter()
+        .map(|(block, mut trips)| {
+            trips.sort();
+            (block, trips.into_iter().map(|(_, trip_key)| trip_key).collect())
+        })
+        .collect()
+}
+
+// Sucht alle Stellen, an denen zwei aufeinanderfolgende zugeordnete Fahrten eines Umlaufs verschiedene Fahrzeuge haben
+pub fn find_block_switches(trips: &HashMap<String, Vec<ScheduleStop>>, result: &MatchResult) -> Vec<BlockSwitch> {
+    let mut switches = Vec::new();
+    for ((datum, umlauf), block_trips) in group_trips_by_block(trips) {
+        let assigned: Vec<(&String, &str)> = block_trips.into_iter()
+            .filter_map(|trip_key| result.vehicle(trip_key).map(|vehicle| (trip_key, vehicle)))
+            .collect();
+        for pair in assigned.windows(2) {
+            let ((from_trip, from_vehicle), (to_trip, to_vehicle)) = (pair[0], pair[1]);
+            if from_vehicle != to_vehicle {
+                switches.push(BlockSwitch {
+                    datum: datum.clone(),
+                    umlauf,
+                    from_trip: from_trip.clone(),
+                    from_vehicle: from_vehicle.to_string(),
+                    to_trip: to_trip.clone(),
+                    to_vehicle: to_vehicle.to_string(),
+                });
+            }
+        }
+    }
+    switches
+}
+
+// Ordnet jeden Umlauf möglichst einem Fahrzeug zu: unter den Fahrzeugen, die Fahrten des Umlaufs erhalten haben, wird
+// das Fahrzeug gewählt, das über alle Fahrten des Umlaufs die meisten Haltestellen abdeckt (bei Gleichstand mit den
+// geringsten Abweichungen). Eine Fahrt wechselt nur dann auf dieses Fahrzeug, wenn es mindestens `min_coverage`
+// ihrer Haltestellen abdeckt; sonst bleibt die Einzelzuordnung und der Wechsel erscheint in find_block_switches.
+pub fn enforce_blocks(matcher: &dyn TripMatcher, trips: &HashMap<String, Vec<ScheduleStop>>, vehicles: &HashMap<String, Vec<VehicleEvent>>, result: MatchResult, min_coverage: f64) -> MatchResult {
+    let blocks: Vec<Vec<&String>> = group_trips_by_block(trips).into_values().collect();
+
+    // Die Umläufe sind unabhängig voneinander und werden parallel bearbeitet
+    let reassigned: Vec<Vec<TripMatch>> = blocks.par_iter()
+        .map(|block_trips| block_vehicle_matches(matcher, trips, vehicles, &result, block_trips, min_coverage))
+        .collect();
+
+    let mut result = result;
+    for trip_match in reassigned.into_iter().flatten() {
+        result.insert(trip_match);
+    }
+    result
+}
+
+// Zuordnungen der Fahrten eines Umlaufs zum gemeinsamen Fahrzeug, soweit sie die Mindestabdeckung erreichen
+fn block_vehicle_matches(matcher: &dyn TripMatcher, trips: &HashMap<String, Vec<ScheduleStop>>, vehicles: &HashMap<String, Vec<VehicleEvent>>, result: &MatchResult, block_trips: &[&String], min_coverage: f64) -> Vec<TripMatch> {
+    let candidates: BTreeSet<&str> = block_trips.iter().filter_map(|trip_key| result.vehicle(trip_key)).collect();
+    if candidates.len() <= 1 && block_trips.iter().all(|trip_key| result.vehicle(trip_key).is_some()) {
+        return Vec::new();
+    }
+
+    // Bewertung jedes Kandidaten über alle Fahrten des Umlaufs
+    let mut best: Option<(usize, f64, Vec<TripMatch>)> = None;
+    for vehicle in candidates {
+        let events = match vehicles.get(vehicle) {
+            Some(events) => events,
+            None => continue,
+        };
+        let matches: Vec<TripMatch> = block_trips.iter()
+            .filter_map(|trip_key| match result.trips.get(*trip_key) {
+                Some(existing) if existing.vehicle == vehicle => Some(existing.clone()),
+                _ => matcher.match_trip_to_vehicle(trip_key, &trips[*trip_key], vehicle, events),
+            })
+            .collect();
+        let covered: usize = matches.iter().map(|trip_match| trip_match.score.stops_covered).sum();
+        let cost: f64 = matches.iter().map(|trip_match| trip_match.score.cost).sum();
+
+        let better = match &best {
+            None => true,
+            Some((best_covered, best_cost, _)) => covered > *best_covered || (covered == *best_covered && cost < *best_cost),
+        };
+        if better {
+            best = Some((covered, cost, matches));
+        }
+    }
+
+    best.map(|(_, _, matches)| {
+        matches.into_iter()
+            .filter(|trip_match| trip_match.score.stops_covered as f64 >= min_coverage * trip_match.score.stops_total as f64)
+            .collect()
+    }).unwrap_or_default()
+}
+
+// Schreibt die Fahrzeugwechsel innerhalb der Umläufe in eine CSV-Datei
+pub fn write_block_switches(switches: &[(String, BlockSwitch)], output_file: &str) -> Result<(), Box<dyn Error>> {
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(output_file)?;
+    writer.write_record(["ALGORITHMUS", "DATUM", "UMLAUF", "FAHRT_VON", "FAHRZEUG_VON", "FAHRT_NACH", "FAHRZEUG_NACH"])?;
+    for (algorithm, switch) in switches {
+        writer.write_record([
+            algorithm.as_str(),
+            switch.datum.as_str(),
+            switch.umlauf.to_string().as_str(),
+            switch.from_trip.as_str(),
+            switch.from_vehicle.as_str(),
+            switch.to_trip.as_str(),
+            switch.to_vehicle.as_str(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,235 @@
+// Konfiguration des Programms: Pfade, Einleseoptionen und Toleranzen aus einer TOML-Datei
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use clap::Args;
+use serde::Deserialize;
+
+use crate::dwell_time::DwellConfig;
+use crate::heuristic_cost::HeuristicCost;
+use crate::matching_params::ParamProfile;
+use crate::od_matrix::OdConfig;
+use crate::passenger_balancing::BalancingConfig;
+use crate::punctuality::PunctualityConfig;
+use crate::rejects::ParseMode;
+use crate::running_time::RunningTimeConfig;
+use crate::schema::{ColumnAliases, InputOptions};
+use crate::time_bands::TimeBandConfig;
+use crate::timestamp::TimeZone;
+
+// Pfade zu den Eingabe-, Zwischen- und Ausgabedateien; jeder Pfad kann auch per Kommandozeile gesetzt werden
+#[derive(Debug, Clone, Default, Deserialize, Args)]
+#[serde(default)]
+pub struct Paths {
+    /// CSV-Datei mit den Rohdaten der Fahrzeuge
+    #[arg(long, global = true)]
+    pub rohdaten: Option<String>,
+    /// CSV-Datei mit dem Fahrplan
+    #[arg(long, global = true)]
+    pub schedule: Option<String>,
+    /// CSV-Datei mit den Vergleichsdaten (Zähldaten)
+    #[arg(long, global = true)]
+    pub zahldaten: Option<String>,
+    /// Ordner für die nach Linien getrennten Fahrplandaten
+    #[arg(long, global = true)]
+    pub fahrplan_ordner: Option<String>,
+    /// Ordner für die nach Fahrzeugen getrennten Busdaten
+    #[arg(long, global = true)]
+    pub busdaten_ordner: Option<String>,
+    /// Ordner für die einzelnen Fahrten (Linienabschnitte)
+    #[arg(long, global = true)]
+    pub fahrten_ordner: Option<String>,
+    /// Ausgabedatei des heuristischen Algorithmus
+    #[arg(long, global = true)]
+    pub output_heuristic: Option<String>,
+    /// Ausgabedatei des VF2-Algorithmus
+    #[arg(long, global = true)]
+    pub output_vf2: Option<String>,
+    /// Zuordnung Fahrt -> Fahrzeug mit Bewertung des VF2-Algorithmus
+    #[arg(long, global = true)]
+    pub zuordnung: Option<String>,
+    /// Zuordnung Fahrt -> Fahrzeug mit Bewertung des heuristischen Algorithmus
+    #[arg(long, global = true)]
+    pub zuordnung_heuristic: Option<String>,
+    /// Datei für die Fahrzeugwechsel innerhalb der Umläufe
+    #[arg(long, global = true)]
+    pub umlauf_wechsel: Option<String>,
+    /// Datei für die Fahrten ohne Fahrzeug in der konfliktfreien Zuordnung
+    #[arg(long, global = true)]
+    pub nicht_zugeordnet: Option<String>,
+    /// Datei für verworfene Zeilen
+    #[arg(long, global = true)]
+    pub rejects: Option<String>,
+    /// Ordner für die aufgeschlüsselte Auswertung (CSV und JSON je Algorithmus)
+    #[arg(long, global = true)]
+    pub auswertung_ordner: Option<String>,
+    /// Ordner für die Analysen der zugeordneten Fahrten (Besetzung usw. je Algorithmus)
+    #[arg(long, global = true)]
+    pub analyse_ordner: Option<String>,
+}
+
+impl Paths {
+    // Werte aus `other` überschreiben die eigenen, sofern sie gesetzt sind
+    pub fn merge(&mut self, other: &Paths) {
+        let overrides = [
+            (&mut self.rohdaten, &other.rohdaten),
+            (&mut self.schedule, &other.schedule),
+            (&mut self.zahldaten, &other.zahldaten),
+            (&mut self.fahrplan_ordner, &other.fahrplan_ordner),
+            (&mut self.busdaten_ordner, &other.busdaten_ordner),
+            (&mut self.fahrten_ordner, &other.fahrten_ordner),
+            (&mut self.output_heuristic, &other.output_heuristic),
+            (&mut self.output_vf2, &other.output_vf2),
+            (&mut self.zuordnung, &other.zuordnung),
+            (&mut self.zuordnung_heuristic, &other.zuordnung_heuristic),
+            (&mut self.umlauf_wechsel, &other.umlauf_wechsel),
+            (&mut self.nicht_zugeordnet, &other.nicht_zugeordnet),
+            (&mut self.rejects, &other.rejects),
+            (&mut self.auswertung_ordner, &other.auswertung_ordner),
+            (&mut self.analyse_ordner, &other.analyse_ordner),
+        ];
+        for (own, other) in overrides {
+            if other.is_some() {
+                *own = other.clone();
+            }
+        }
+    }
+}
+
+// Liefert einen Pfad oder einen Fehler mit dem Namen der fehlenden Einstellung
+pub fn require<'a>(path: &'a Option<String>, name: &str) -> Result<&'a str, Box<dyn Error>> {
+    path.as_deref().ok_or_else(|| {
+        format!("Pfad '{}' fehlt: per --{} oder unter [paths] in der Konfiguration angeben", name, name.replace('_', "-")).into()
+    })
+}
+
+// Einstellungen für das Einlesen der Eingabedateien
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    pub lenient: bool,                    // Fehlerhafte Zeilen verwerfen statt abzubrechen
+    pub aliases: HashMap<String, String>, // Alternativer Spaltenname -> Spaltenname des Datenmodells
+    pub aliases_file: Option<String>,     // CSV-Datei mit weiteren Aliasen (ALIAS;SPALTE)
+    pub utc_offset_minutes: Option<i32>,  // Fester Abstand der Ortszeit zu UTC für Tageszeiten aus ZEIT, sonst MEZ/MESZ
+}
+
+impl InputConfig {
+    // Zeitzone der Tageszeiten aus ZEIT: ohne `utc_offset_minutes` Europe/Berlin mit Sommerzeit
+    pub fn time_zone(&self) -> TimeZone {
+        self.utc_offset_minutes.map_or(TimeZone::EuropeBerlin, TimeZone::Fixed)
+    }
+
+    pub fn to_input_options(&self) -> Result<InputOptions, Box<dyn Error>> {
+        let mut aliases = match &self.aliases_file {
+            Some(file) => ColumnAliases::from_file(file)?,
+            None => ColumnAliases::new(),
+        };
+        for (alias, column) in &self.aliases {
+            aliases.insert(alias, column);
+        }
+        let mode = if self.lenient { ParseMode::Lenient } else { ParseMode::Strict };
+        Ok(InputOptions { aliases, mode, ..Default::default() })
+    }
+}
+
+// Toleranzen der Algorithmen und der Auswertung
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Tolerances {
+    pub time_tolerance_seconds: u64, // Maximale Verspätung gegenüber der erwarteten Zeit (Maßstab der Zeitabweichung)
+    pub early_tolerance_seconds: u64, // Maximale Verfrühung gegenüber der erwarteten Zeit
+    pub propagate_delay: bool,       // Erwartete Zeit übernimmt die Verspätung der vorherigen Haltestelle
+    pub candidate_radius_m: f64,     // Suchradius um eine Haltestelle für Kandidatenereignisse (Heuristik)
+    pub max_delay_seconds: u64,      // Maximale Abweichung der Fahrzeit zwischen zwei Haltestellen (VF2)
+    pub match_distance_m: f64,       // Maximale Entfernung zwischen Haltestelle und Fahrzeugereignis (VF2)
+    pub max_skipped_stops: usize,    // Haltestellen einer Fahrt, die ohne Ereignis bleiben dürfen (VF2)
+    pub max_search_states: usize,    // Obergrenze der untersuchten Zustände je Fahrt und Fahrzeug (VF2)
+    pub evaluation_distance_m: f64,  // Maximale Entfernung zur Referenzposition in der Auswertung
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Tolerances {
+            time_tolerance_seconds: 10 * 60,
+            early_tolerance_seconds: 3 * 60,
+            propagate_delay: true,
+            candidate_radius_m: 500.0,
+            max_delay_seconds: 5 * 60,
+            match_distance_m: 200.0,
+            max_skipped_stops: 2,
+            max_search_states: 100_000,
+            evaluation_distance_m: 20.0,
+        }
+    }
+}
+
+// Einstellungen für den Gesamtlauf (Befehl `all`) und die Parallelisierung
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    pub write_intermediate: bool, // Zwischenordner (fahrplan, busdaten, fahrten) zur Fehlersuche schreiben
+    pub threads: usize,           // Anzahl der Threads für Zuordnung und Auswertung (0 = alle Kerne)
+}
+
+// Einstellungen der Zuordnung
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MatchingConfig {
+    pub block_consistency: bool, // Fahrten eines Umlaufs möglichst demselben Fahrzeug zuordnen
+    pub min_block_coverage: f64, // Mindestanteil der Haltestellen einer Fahrt, die das Fahrzeug des Umlaufs abdecken muss
+    pub conflict_free: bool,     // Kein Fahrzeug erhält zwei sich zeitlich überschneidende Fahrten
+}
+
+impl Default for MatchingConfig {
+    fn default() -> Self {
+        MatchingConfig {
+            block_consistency: false,
+            min_block_coverage: 0.5,
+            conflict_free: false,
+        }
+    }
+}
+
+// Einstellungen der Auswertung
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EvaluationConfig {
+    pub worst_trips: usize,     // Anzahl der schlechtesten Fahrten in der Aufschlüsselung
+    pub count_tolerance: f64,   // Zulässige relative Abweichung der Fahrgastzahlen je Fahrt (0.1 = ±10 %)
+}
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        EvaluationConfig { worst_trips: 20, count_tolerance: 0.1 }
+    }
+}
+
+// Gesamte Konfiguration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub paths: Paths,
+    pub input: InputConfig,
+    pub tolerances: Tolerances,
+    pub matching: MatchingConfig,
+    pub heuristic: HeuristicCost,
+    pub pipeline: PipelineConfig,
+    pub evaluation: EvaluationConfig,
+    pub balancing: BalancingConfig,
+    pub od: OdConfig,
+    pub time_bands: TimeBandConfig,
+    pub punctuality: PunctualityConfig,
+    pub dwell: DwellConfig,
+    pub running_times: RunningTimeConfig,
+    pub profiles: Vec<ParamProfile>, // Abweichende Toleranzen je Linie, Richtung und Zeitband ([[profiles]])
+}
+
+// Funktion zum Einlesen der Konfigurationsdatei
+pub fn load_config<P: AsRef<Path>>(file_path: P) -> Result<Config, Box<dyn Error>> {
+    let content = fs::read_to_string(file_path.as_ref())
+        .map_err(|err| format!("Konfiguration {} kann nicht gelesen werden: {}", file_path.as_ref().display(), err))?;
+    let config: Config = toml::from_str(&content)
+        .map_err(|err| format!("Konfiguration {} ist ungültig: {}", file_path.as_ref().display(), err))?;
+    Ok(config)
+}
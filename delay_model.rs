@@ -0,0 +1,49 @@
+// Zeitliches Modell der Zuordnung: Busse sind selten zu früh, aber oft zunehmend verspätet. Das Zeitfenster einer
+// Haltestelle reicht deshalb von `early_tolerance_seconds` vor bis `time_tolerance_seconds` nach der erwarteten Zeit,
+// und die erwartete Zeit übernimmt (mit `propagate_delay`) die Verspätung der zuletzt zugeordneten Haltestelle.
+use crate::config::Tolerances;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DelayTracker<'a> {
+    tolerances: &'a Tolerances,
+    expected_delay: i64, // Erwartete Verspätung in Sekunden (negativ = zu früh)
+}
+
+impl<'a> DelayTracker<'a> {
+    // Erwartung am Anfang einer Fahrt: pünktlich
+    pub fn new(tolerances: &'a Tolerances) -> Self {
+        DelayTracker { tolerances, expected_delay: 0 }
+    }
+
+    // Erwartung nach einem zugeordneten Paar aus Soll- und Ist-Zeit (sofern vorhanden)
+    pub fn after(tolerances: &'a Tolerances, last: Option<(u64, u64)>) -> Self {
+        let mut tracker = DelayTracker::new(tolerances);
+        if let Some((scheduled, actual)) = last {
+            tracker.observe(scheduled, actual);
+        }
+        tracker
+    }
+
+    // Übernimmt die beobachtete Verspätung als Erwartung für die folgenden Haltestellen
+    pub fn observe(&mut self, scheduled: u64, actual: u64) {
+        if self.tolerances.propagate_delay {
+            self.expected_delay = actual as i64 - scheduled as i64;
+        }
+    }
+
+    // Erwartete Ist-Zeit einer Haltestelle mit der Soll-Zeit `scheduled`
+    pub fn expected_time(&self, scheduled: u64) -> u64 {
+        (scheduled as i64 + self.expected_delay).max(0) as u64
+    }
+
+    // Zulässiges Zeitfenster [von, bis] für eine Haltestelle mit der Soll-Zeit `scheduled`
+    pub fn window(&self, scheduled: u64) -> (u64, u64) {
+        let expected = self.expected_time(scheduled);
+        (expected.saturating_sub(self.tolerances.early_tolerance_seconds), expected + self.tolerances.time_tolerance_seconds)
+    }
+
+    pub fn accepts(&self, scheduled: u64, actual: u64) -> bool {
+        let (from, to) = self.window(scheduled);
+        from <= actual && actual <= to
+    }
+}
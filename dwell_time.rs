@@ -0,0 +1,203 @@
+// Haltezeiten der zugeordneten Fahrten: `zeit` bis `zeit_next` des zugeordneten Ereignisses je Haltestelle, zusammen
+// mit den Ein- und Aussteigern. Je Haltestelle und Zeitband wird zusätzlich eine Gerade
+// Haltezeit = Grundzeit + Sekunden je Fahrgast * (Einsteiger + Aussteiger) angepasst (kleinste Quadrate).
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use csv::WriterBuilder;
+use serde::Deserialize;
+
+use crate::matcher::MatchedTrips;
+use crate::time_bands::TimeBands;
+
+// Einstellungen der Haltezeiten ([dwell] in der Konfiguration)
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct DwellConfig {
+    pub max_seconds: i64, // Längere Halte (z.B. Wendezeiten) werden nicht ausgewertet
+}
+
+impl Default for DwellConfig {
+    fn default() -> Self {
+        DwellConfig { max_seconds: 600 }
+    }
+}
+
+// Haltezeit an einer zugeordneten Haltestelle
+#[derive(Debug, Clone, PartialEq)]
+pub struct DwellEvent {
+    pub trip_key: String,
+    pub vehicle: String,
+    pub line: u32,
+    pub richtung: u32,
+    pub schedule_id: String,
+    pub lfnr: u32,
+    pub ort_nr: u32,
+    pub ort_name: String,
+    pub time_band: String,
+    pub typ: String,
+    pub dwell_s: i64,
+    pub boardings: u32,
+    pub alightings: u32,
+}
+
+impl DwellEvent {
+    pub fn passengers(&self) -> u32 {
+        self.boardings + self.alightings
+    }
+}
+
+// Ergebnis der Geraden Haltezeit = intercept_s + seconds_per_passenger * Fahrgäste
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DwellFit {
+    pub intercept_s: f64,
+    pub seconds_per_passenger: f64,
+}
+
+// Haltezeiten einer Haltestelle in einem Zeitband
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DwellStats {
+    pub ort_name: String,
+    pub events: usize,
+    pub dwell_sum_s: i64,
+    pub max_dwell_s: i64,
+    pub boardings: u64,
+    pub alightings: u64,
+    pub fit: Option<DwellFit>,
+}
+
+impl DwellStats {
+    pub fn mean_dwell_s(&self) -> f64 {
+        if self.events == 0 { 0.0 } else { self.dwell_sum_s as f64 / self.events as f64 }
+    }
+}
+
+// Haltezeiten aller zugeordneten Haltestellen; liefert außerdem die Zahl der Halte ohne auswertbare Haltezeit
+// (kein `zeit_next`, `zeit_next` vor `zeit` oder länger als `max_seconds`)
+pub fn dwell_events(trips: &MatchedTrips, time_bands: &TimeBands, config: &DwellConfig) -> (Vec<DwellEvent>, usize) {
+    let mut events = Vec::new();
+    let mut skipped = 0;
+    for (trip_key, stops) in trips {
+        for (stop, event) in stops {
+            let dwell_s = match event.zeit_next {
+                Some(zeit_next) => zeit_next.seconds() - event.zeit.seconds(),
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            if dwell_s < 0 || dwell_s > config.max_seconds {
+                skipped += 1;
+                continue;
+            }
+            events.push(DwellEvent {
+                trip_key: trip_key.clone(),
+                vehicle: event.vehicle.clone(),
+                line: stop.line,
+                richtung: stop.richtung,
+                schedule_id: stop.schedule_id.clone(),
+                lfnr: stop.lfnr,
+                ort_nr: stop.ort_nr,
+                ort_name: stop.ort_name.clone(),
+                time_band: time_bands.stop_label(stop),
+                typ: event.typ.clone(),
+                dwell_s,
+                boardings: event.einsteiger,
+                alightings: event.aussteiger,
+            });
+        }
+    }
+    (events, skipped)
+}
+
+// Kleinste-Quadrate-Gerade über die Halte; None bei weniger als zwei Halten oder gleicher Fahrgastzahl an allen Halten
+pub fn fit<'a>(events: impl IntoIterator<Item = &'a DwellEvent>) -> Option<DwellFit> {
+    let points: Vec<(f64, f64)> = events.into_iter().map(|event| (event.passengers() as f64, event.dwell_s as f64)).collect();
+    if points.len() < 2 {
+        return None;
+    }
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if variance <= f64::EPSILON {
+        return None;
+    }
+    let seconds_per_passenger = covariance / variance;
+    Some(DwellFit { intercept_s: mean_y - seconds_per_passenger * mean_x, seconds_per_passenger })
+}
+
+// Haltezeiten je (ORT_NR, Zeitband)
+pub fn aggregate(events: &[DwellEvent]) -> BTreeMap<(u32, String), DwellStats> {
+    let mut groups: BTreeMap<(u32, String), Vec<&DwellEvent>> = BTreeMap::new();
+    for event in events {
+        groups.entry((event.ort_nr, event.time_band.clone())).or_default().push(event);
+    }
+    groups.into_iter().map(|(key, group)| {
+        let stats = DwellStats {
+            ort_name: group[0].ort_name.clone(),
+            events: group.len(),
+            dwell_sum_s: group.iter().map(|event| event.dwell_s).sum(),
+            max_dwell_s: group.iter().map(|event| event.dwell_s).max().unwrap_or(0),
+            boardings: group.iter().map(|event| event.boardings as u64).sum(),
+            alightings: group.iter().map(|event| event.alightings as u64).sum(),
+            fit: fit(group.iter().copied()),
+        };
+        (key, stats)
+    }).collect()
+}
+
+// Schreibt {algorithmus}_haltezeiten.csv (je Halt) und {algorithmus}_haltezeiten_haltestellen.csv (je Haltestelle und
+// Zeitband) in den Ordner `output_folder`
+pub fn write_dwell_times(algorithm: &str, events: &[DwellEvent], stats: &BTreeMap<(u32, String), DwellStats>, output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let folder = Path::new(output_folder);
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_haltezeiten.csv", algorithm)))?;
+    writer.write_record([
+        "FAHRT", "FAHRZEUG", "LINIE", "RICHTUNG", "SCHEDULE_ID", "LFD_NR", "ORT_NR", "ORT_NAME", "ZEITBAND", "TYP",
+        "HALTEZEIT_S", "EINSTEIGER", "AUSSTEIGER",
+    ])?;
+    for event in events {
+        writer.write_record([
+            event.trip_key.clone(),
+            event.vehicle.clone(),
+            event.line.to_string(),
+            event.richtung.to_string(),
+            event.schedule_id.clone(),
+            event.lfnr.to_string(),
+            event.ort_nr.to_string(),
+            event.ort_name.clone(),
+            event.time_band.clone(),
+            event.typ.clone(),
+            event.dwell_s.to_string(),
+            event.boardings.to_string(),
+            event.alightings.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_haltezeiten_haltestellen.csv", algorithm)))?;
+    writer.write_record([
+        "ORT_NR", "ORT_NAME", "ZEITBAND", "HALTE", "MITTLERE_HALTEZEIT_S", "MAX_HALTEZEIT_S", "EINSTEIGER", "AUSSTEIGER",
+        "GRUNDZEIT_S", "SEKUNDEN_JE_FAHRGAST",
+    ])?;
+    for ((ort_nr, time_band), stats) in stats {
+        writer.write_record([
+            ort_nr.to_string(),
+            stats.ort_name.clone(),
+            time_band.clone(),
+            stats.events.to_string(),
+            format!("{:.1}", stats.mean_dwell_s()),
+            stats.max_dwell_s.to_string(),
+            stats.boardings.to_string(),
+            stats.alightings.to_string(),
+            stats.fit.map(|fit| format!("{:.2}", fit.intercept_s)).unwrap_or_default(),
+            stats.fit.map(|fit| format!("{:.2}", fit.seconds_per_passenger)).unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
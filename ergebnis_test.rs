@@ -1,10 +1,18 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
-use std::fs;
-use std::io::{self, BufRead};
+use std::path::Path;
 extern crate geoutils; // Externes Paket für geografische Berechnungen
-use geoutils::{Location, Distance};
+use geoutils::Location;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+use crate::matcher::MatchResult;
+use crate::matching_params::{first_stop, MatchingParams};
+use crate::model::{ScheduleStop, VehicleEvent};
+use crate::schema::{self, InputOptions};
+
+#[derive(Debug, Deserialize)]
 pub struct Record {
     schedule_id: String,
     fahrzeug: String,
@@ -13,91 +21,264 @@ pub struct Record {
     // Weitere Felder hinzufügen, die in den Dateien vorhanden sind
 }
 
-pub fn load_data_output(filename: &str) -> io::Result<Vec<Record>> {
-    let file = fs::File::open(filename)?;
-    let reader = io::BufReader::new(file);
-    let mut records = Vec::new();
+// Zugeordnete Haltestelle eines Algorithmus mit der Entfernungstoleranz ihrer Fahrt
+#[derive(Debug)]
+pub struct OutputStop {
+    schedule_id: String,
+    fahrzeug: String,
+    lon: f64,
+    lat: f64,
+    evaluation_distance_m: f64,
+}
 
-    // Iteriere über die Datensätze
-    for line in reader.lines().skip(1) {
-        let line = line?;
-        let fields: Vec<&str> = line.split(';').collect();
-
-        let record = Record {
-            schedule_id: fields[0].to_string(),
-            fahrzeug: fields[18].to_string(), //17
-            lon: fields[13].parse().unwrap(), //22
-            lat: fields[14].parse().unwrap(), //21
-            
-            // Hier weitere Felder entsprechend einfügen
-        };
-        records.push(record);
+// Auswertung einer Fahrt: erwartetes Fahrzeug (Vergleichsdaten) und zugeordnetes Fahrzeug (Ergebnis)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TripEvaluation {
+    pub trip_key: String,
+    pub line: u32,
+    pub richtung: u32,
+    pub hour: Option<u32>,                // Stunde der Fahrtanfangszeit
+    pub expected_vehicle: Option<String>, // Fahrzeug laut Vergleichsdaten
+    pub assigned_vehicle: Option<String>, // Fahrzeug laut Ergebnis
+    pub reference_stops: usize,           // Haltestellen der Fahrt in den Vergleichsdaten
+    pub output_stops: usize,              // Haltestellen der Fahrt im Ergebnis
+    pub vehicle_correct_stops: usize,     // Gemeinsame Haltestellen mit gleichem Fahrzeug
+    pub within_tolerance_stops: usize,    // Davon innerhalb der Entfernungstoleranz
+}
+
+impl TripEvaluation {
+    // Das Ergebnis hat der Fahrt das erwartete Fahrzeug zugeordnet
+    pub fn is_correct(&self) -> bool {
+        self.expected_vehicle.is_some() && self.expected_vehicle == self.assigned_vehicle
+    }
+
+    // Anteil der Haltestellen aus den Vergleichsdaten mit gleichem Fahrzeug innerhalb der Toleranz
+    pub fn stop_accuracy(&self) -> f64 {
+        ratio(self.within_tolerance_stops, self.reference_stops)
     }
+}
 
-    Ok(records)
+// Ergebnis der Auswertung eines Algorithmus gegen die Vergleichsdaten
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EvaluationReport {
+    pub output_stops: usize,              // Haltestellen im Ergebnis (je SCHEDULE_ID einmal)
+    pub reference_stops: usize,           // Haltestellen in den Vergleichsdaten (je SCHEDULE_ID einmal)
+    pub duplicate_output_stops: usize,    // Weitere Zeilen mit bereits gezählter SCHEDULE_ID im Ergebnis
+    pub duplicate_reference_stops: usize, // Weitere Zeilen mit bereits gezählter SCHEDULE_ID in den Vergleichsdaten
+    pub joined_stops: usize,              // Haltestellen in beiden
+    pub vehicle_correct_stops: usize,     // Gemeinsame Haltestellen mit gleichem Fahrzeug
+    pub within_tolerance_stops: usize,    // Davon innerhalb der Entfernungstoleranz
+    pub output_trips: usize,              // Fahrten mit Fahrzeug im Ergebnis
+    pub reference_trips: usize,           // Fahrten mit Fahrzeug in den Vergleichsdaten
+    pub correct_trips: usize,             // Fahrten mit demselben Fahrzeug in beiden
+    pub precision: f64,                   // Richtige Fahrten / Fahrten im Ergebnis
+    pub recall: f64,                      // Richtige Fahrten / Fahrten in den Vergleichsdaten
+    pub f1: f64,
+    pub within_tolerance_share: f64,      // Anteil der gemeinsamen Haltestellen mit gleichem Fahrzeug innerhalb der Toleranz
+    #[serde(skip)]
+    pub trips: Vec<TripEvaluation>,       // Je Fahrt aus Ergebnis oder Vergleichsdaten, nach Fahrt sortiert
 }
 
-pub fn load_data_comparison(filename: &str) -> io::Result<Vec<Record>> {
-    let file = fs::File::open(filename)?;
-    let reader = io::BufReader::new(file);
-    let mut records = Vec::new();
+impl EvaluationReport {
+    pub fn print(&self) {
+        println!("Haltestellen: {} im Ergebnis, {} in den Vergleichsdaten, {} gemeinsam, {} mit gleichem Fahrzeug.",
+            self.output_stops, self.reference_stops, self.joined_stops, self.vehicle_correct_stops);
+        if self.duplicate_output_stops > 0 || self.duplicate_reference_stops > 0 {
+            println!("Doppelte SCHEDULE_IDs nicht gezählt: {} im Ergebnis, {} in den Vergleichsdaten.",
+                self.duplicate_output_stops, self.duplicate_reference_stops);
+        }
+        println!("Fahrten: {} im Ergebnis, {} in den Vergleichsdaten, {} richtig zugeordnet.",
+            self.output_trips, self.reference_trips, self.correct_trips);
+        println!("Precision: {:.2}%, Recall: {:.2}%, F1: {:.2}%", self.precision * 100.0, self.recall * 100.0, self.f1 * 100.0);
+        println!("Gemeinsame Haltestellen mit gleichem Fahrzeug innerhalb der Entfernungstoleranz: {:.2}%", self.within_tolerance_share * 100.0);
+    }
+}
 
-    // Iteriere über die Datensätze
-    for line in reader.lines().skip(1) {
-        let line = line?;
-        let fields: Vec<&str> = line.split(';').collect();
+// Wandelt die zugeordneten Haltestellen eines Algorithmus in Vergleichsdatensätze um; die Entfernungstoleranz kommt aus
+// dem Profil der Fahrt im Fahrplan (Haltestellen ohne bekannte Fahrt: Profil der einzelnen Haltestelle)
+pub fn records_from_matched(matched: &[(ScheduleStop, VehicleEvent)], trips: &HashMap<String, Vec<ScheduleStop>>, params: &MatchingParams) -> Vec<OutputStop> {
+    let evaluation_distance_m: HashMap<&str, f64> = trips.values()
+        .flat_map(|stops| {
+            let distance_m = params.for_trip(stops).evaluation_distance_m;
+            stops.iter().map(move |stop| (stop.schedule_id.as_str(), distance_m))
+        })
+        .collect();
+    let mut records = Vec::new();
 
-        let record = Record {
-            schedule_id: fields[0].to_string(),
-            fahrzeug: fields[20].to_string(), 
-            lon: fields[14].parse().unwrap(),
-            lat: fields[15].parse().unwrap(), 
+    // Iteriere über die zugeordneten Haltestellen der Ergebnisdatei
+    for (stop, event) in matched {
+        let record = OutputStop {
+            schedule_id: stop.schedule_id.clone(),
+            fahrzeug: event.vehicle.clone(),
+            lon: event.lon,
+            lat: event.lat,
+            evaluation_distance_m: evaluation_distance_m.get(stop.schedule_id.as_str()).copied()
+                .unwrap_or_else(|| params.for_trip([stop]).evaluation_distance_m),
         };
         records.push(record);
     }
 
-    Ok(records)
+    records
+}
+
+// Vergleicht das Ergebnis eines Algorithmus direkt mit den Vergleichsdaten
+pub fn evaluate_result(result: &MatchResult, comparison_data: &[Record], trips: &HashMap<String, Vec<ScheduleStop>>, params: &MatchingParams) -> EvaluationReport {
+    compare_records(&records_from_matched(&result.stops(), trips, params), comparison_data, trips, params)
+}
+
+// Spalten der Vergleichsdatei in der Reihenfolge der Felder von Record
+pub const COMPARISON_COLUMNS: &[&str] = &["SCHEDULE_ID", "FAHRZEUG", "LON", "LAT"];
+
+pub fn load_data_comparison(filename: &str, options: &InputOptions) -> Result<Vec<Record>, Box<dyn Error>> {
+    // Die Spalten werden über die Kopfzeile gesucht
+    schema::read_mapped_records(Path::new(filename), COMPARISON_COLUMNS, options)
+}
+
+
+// Vergleicht die eingelesene Ergebnisdatei eines Algorithmus (`matched`) mit der Vergleichsdatei
+pub fn test_results(matched: &[(ScheduleStop, VehicleEvent)], comparison_data: &str, trips: &HashMap<String, Vec<ScheduleStop>>, options: &InputOptions, params: &MatchingParams) -> Result<EvaluationReport, Box<dyn Error>> {
+    // Laden der Vergleichsdaten aus der Datei
+    let output_data = records_from_matched(matched, trips, params);
+    let comparison_data = load_data_comparison(comparison_data, options)?;
+
+    Ok(compare_records(&output_data, &comparison_data, trips, params))
 }
 
+// Vergleicht die Datensätze eines Algorithmus mit den Vergleichsdaten. Beide Seiten werden über die SCHEDULE_ID
+// verknüpft (je SCHEDULE_ID zählt die erste Zeile); die Fahrt einer Haltestelle kommt aus den Fahrten des Fahrplans.
+pub fn compare_records(output_data: &[OutputStop], comparison_data: &[Record], trips: &HashMap<String, Vec<ScheduleStop>>, params: &MatchingParams) -> EvaluationReport {
+    let mut report = EvaluationReport::default();
+
+    // SCHEDULE_ID -> Fahrt
+    let trip_of: HashMap<&str, &str> = trips.iter()
+        .flat_map(|(trip_key, stops)| stops.iter().map(move |stop| (stop.schedule_id.as_str(), trip_key.as_str())))
+        .collect();
+
+    let mut output_by_stop: HashMap<&str, &OutputStop> = HashMap::new();
+    for output_record in output_data {
+        match output_by_stop.entry(output_record.schedule_id.as_str()) {
+            Entry::Occupied(_) => report.duplicate_output_stops += 1,
+            Entry::Vacant(entry) => {
+                entry.insert(output_record);
+            }
+        }
+    }
+    let mut reference_by_stop: HashMap<&str, &Record> = HashMap::new();
+    for comparison_record in comparison_data {
+        match reference_by_stop.entry(comparison_record.schedule_id.as_str()) {
+            Entry::Occupied(_) => report.duplicate_reference_stops += 1,
+            Entry::Vacant(entry) => {
+                entry.insert(comparison_record);
+            }
+        }
+    }
+    report.output_stops = output_by_stop.len();
+    report.reference_stops = reference_by_stop.len();
+
+    // Haltestellen aus Ergebnis und Vergleichsdaten je Fahrt (None: SCHEDULE_ID ohne Fahrt im Fahrplan)
+    let mut stops_by_trip: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+    for schedule_id in output_by_stop.keys().chain(reference_by_stop.keys().filter(|schedule_id| !output_by_stop.contains_key(*schedule_id))) {
+        stops_by_trip.entry(trip_of.get(schedule_id).copied()).or_default().push(schedule_id);
+    }
+
+    // Vergleich der einzelnen Haltestellen, je Fahrt parallel; je Fahrt die Zähler und die Zahl der gemeinsamen Haltestellen
+    let evaluated: Vec<(Option<&str>, TripEvaluation, usize)> = stops_by_trip.into_par_iter().map(|(trip_key, schedule_ids)| {
+        let mut trip = TripEvaluation::default();
+        let mut joined = 0;
+        for schedule_id in schedule_ids {
+            let output_record = output_by_stop.get(schedule_id);
+            let comparison_record = reference_by_stop.get(schedule_id);
+            trip.output_stops += output_record.is_some() as usize;
+            trip.reference_stops += comparison_record.is_some() as usize;
+            let (output_record, comparison_record) = match (output_record, comparison_record) {
+                (Some(output_record), Some(comparison_record)) => (output_record, comparison_record),
+                _ => continue,
+            };
+            joined += 1;
+            if output_record.fahrzeug != comparison_record.fahrzeug {
+                continue;
+            }
+            trip.vehicle_correct_stops += 1;
 
-pub fn test_results(output_data: &str, comparison_data: &str) -> io::Result<f64> {
-    // Laden der Daten aus den Dateien
-    let output_data = load_data_output(output_data)?;
-    let comparison_data = load_data_comparison(comparison_data)?;
-
-    // Gesamtanzahl der Datensätze
-    let total_records = output_data.len();
-    let mut matched_records = 0.0;
-
-    // Durchlaufen der Datensätze in der Ausgabedatei
-    for output_record in &output_data {
-        for comparison_record in &comparison_data {
-            // Überprüfung der Übereinstimmung der ersten Spalte
-            if output_record.schedule_id == comparison_record.schedule_id {
-                matched_records += 0.333333;
-                // Überprüfung der Übereinstimmung der zweiten Spalte
-                if output_record.fahrzeug == comparison_record.fahrzeug {
-                    matched_records += 0.333333;
-                    // Extrahieren der Breitengrade und Längengrade aus der dritten und vierten Spalte
-                    let output_lat_lon = Location::new(output_record.lat, output_record.lon);
-                    let comparison_lat_lon = Location::new(comparison_record.lat, comparison_record.lon);
-
-                    // Berechnung der Distanz zwischen den Koordinaten und Überprüfung der Toleranz von 50 Metern
-                    if let Ok(output_distance) = output_lat_lon.distance_to(&comparison_lat_lon) {
-                        if output_distance.meters() <= 20.0 {
-                            matched_records += 0.333333;
-                            // Behandlung des Falls, wenn die Distanz weniger als oder gleich 20,0 Meter beträgt
-                        }
-                    }
+            // Berechnung der Distanz zwischen den Koordinaten und Überprüfung der Toleranz (Standard 20 Meter, je Profil)
+            let output_lat_lon = Location::new(output_record.lat, output_record.lon);
+            let comparison_lat_lon = Location::new(comparison_record.lat, comparison_record.lon);
+            if let Ok(output_distance) = output_lat_lon.distance_to(&comparison_lat_lon) {
+                if output_distance.meters() <= output_record.evaluation_distance_m {
+                    trip.within_tolerance_stops += 1;
                 }
             }
         }
+        (trip_key, trip, joined)
+    }).collect();
+
+    // Zusammenführen der Fahrten; Haltestellen ohne Fahrt zählen nur in den Summen
+    let mut per_trip: BTreeMap<&str, TripEvaluation> = BTreeMap::new();
+    for (trip_key, trip, joined) in evaluated {
+        report.joined_stops += joined;
+        report.vehicle_correct_stops += trip.vehicle_correct_stops;
+        report.within_tolerance_stops += trip.within_tolerance_stops;
+        if let Some(trip_key) = trip_key {
+            per_trip.insert(trip_key, trip);
+        }
+    }
+
+    // Vergleich der Zuordnung Fahrt -> Fahrzeug
+    let output_trips = trip_vehicles(output_by_stop.iter().map(|(schedule_id, record)| (*schedule_id, record.fahrzeug.as_str())), &trip_of);
+    let reference_trips = trip_vehicles(reference_by_stop.iter().map(|(schedule_id, record)| (*schedule_id, record.fahrzeug.as_str())), &trip_of);
+    report.output_trips = output_trips.len();
+    report.reference_trips = reference_trips.len();
+    report.correct_trips = output_trips.iter()
+        .filter(|(trip_key, vehicle)| reference_trips.get(*trip_key) == Some(vehicle))
+        .count();
+
+    report.precision = ratio(report.correct_trips, report.output_trips);
+    report.recall = ratio(report.correct_trips, report.reference_trips);
+    report.f1 = f1(report.precision, report.recall);
+    report.within_tolerance_share = ratio(report.within_tolerance_stops, report.joined_stops);
+
+    // Auswertung je Fahrt mit Linie, Richtung und Stunde aus dem Fahrplan
+    report.trips = per_trip.into_iter().map(|(trip_key, mut trip)| {
+        if let Some(first) = trips.get(trip_key).and_then(first_stop) {
+            trip.line = first.line;
+            trip.richtung = first.richtung;
+            trip.hour = params.local_minute(first.zeit).map(|minute| minute / 60);
+        }
+        trip.trip_key = trip_key.to_string();
+        trip.expected_vehicle = reference_trips.get(trip_key).map(|vehicle| vehicle.to_string());
+        trip.assigned_vehicle = output_trips.get(trip_key).map(|vehicle| vehicle.to_string());
+        trip
+    }).collect();
+    report
+}
+
+// Fahrzeug je Fahrt: das Fahrzeug mit den meisten Haltestellen der Fahrt (bei Gleichstand die kleinere Kennung);
+// Haltestellen ohne bekannte Fahrt bleiben unberücksichtigt
+fn trip_vehicles<'a>(stops: impl Iterator<Item = (&'a str, &'a str)>, trip_of: &HashMap<&str, &'a str>) -> BTreeMap<&'a str, &'a str> {
+    let mut counts: BTreeMap<&str, BTreeMap<&str, usize>> = BTreeMap::new();
+    for (schedule_id, vehicle) in stops {
+        if let Some(trip_key) = trip_of.get(schedule_id) {
+            *counts.entry(trip_key).or_default().entry(vehicle).or_insert(0) += 1;
+        }
     }
-    // Ausgabe der Anzahl der übereinstimmenden Paare und der Gesamtanzahl der Daten
-    println!("Gleiche Paare: {:?}. Gesamte Daten: {:}", matched_records, total_records);
-    // Berechnung des prozentualen Anteils der übereinstimmenden Paare an der Gesamtanzahl der Daten
-    let percentage = (matched_records as f64 / total_records as f64) * 100.0;
-    println!("Prozentuale Differenz: {:.2}%", percentage);
-    Ok(percentage)
+    counts.into_iter()
+        .filter_map(|(trip_key, vehicles)| {
+            vehicles.into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+                .map(|(vehicle, _)| (trip_key, vehicle))
+        })
+        .collect()
 }
 
+pub fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { part as f64 / total as f64 }
+}
+
+// Harmonisches Mittel aus Precision und Recall
+pub fn f1(precision: f64, recall: f64) -> f64 {
+    if precision + recall > 0.0 {
+        2.0 * precision * recall / (precision + recall)
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,212 @@
+// Aufschlüsselung der Auswertung: Precision, Recall und F1 je Linie, Richtung, Stunde und Fahrzeug, die Verwechslungen
+// (erwartetes Fahrzeug, zugeordnetes Fahrzeug) und die schlechtesten Fahrten, als CSV und JSON. Damit lässt sich
+// erkennen, für welche Linien andere Toleranzen nötig sind.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+use csv::WriterBuilder;
+use serde::Serialize;
+
+use crate::ergebnis_test::{f1, ratio, EvaluationReport, TripEvaluation};
+
+// Merkmale, nach denen die Fahrten gruppiert werden
+const DIMENSIONS: [&str; 4] = ["linie", "richtung", "stunde", "fahrzeug"];
+
+// Kennzahlen einer Gruppe von Fahrten
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BreakdownRow {
+    pub dimension: String,             // linie, richtung, stunde oder fahrzeug
+    pub key: String,                   // Wert des Merkmals
+    pub reference_trips: usize,        // Fahrten mit Fahrzeug in den Vergleichsdaten
+    pub output_trips: usize,           // Fahrten mit Fahrzeug im Ergebnis
+    pub correct_trips: usize,          // Fahrten mit demselben Fahrzeug in beiden
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub reference_stops: usize,        // Haltestellen der Fahrten in den Vergleichsdaten
+    pub within_tolerance_stops: usize, // Davon mit gleichem Fahrzeug innerhalb der Entfernungstoleranz
+    pub stop_share: f64,
+}
+
+// Falsch zugeordnete Fahrten: fehlt das erwartete Fahrzeug, ist die Fahrt nicht in den Vergleichsdaten;
+// fehlt das zugeordnete, hat der Algorithmus die Fahrt nicht zugeordnet
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfusionPair {
+    pub expected_vehicle: Option<String>,
+    pub assigned_vehicle: Option<String>,
+    pub trips: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Breakdown {
+    pub rows: Vec<BreakdownRow>,
+    pub confusion: Vec<ConfusionPair>,
+    pub worst_trips: Vec<TripEvaluation>,
+}
+
+// Schlüssel einer Fahrt für ein Merkmal, getrennt für die Seite der Vergleichsdaten und die des Ergebnisses; beim
+// Fahrzeug zählt eine Fahrt für das erwartete Fahrzeug im Recall und für das zugeordnete in der Precision
+fn keys(dimension: &str, trip: &TripEvaluation) -> (Option<String>, Option<String>) {
+    let key = match dimension {
+        "linie" => trip.line.to_string(),
+        "richtung" => trip.richtung.to_string(),
+        "stunde" => trip.hour.map_or_else(|| "unbekannt".to_string(), |hour| format!("{:02}", hour)),
+        _ => return (trip.expected_vehicle.clone(), trip.assigned_vehicle.clone()),
+    };
+    (Some(key.clone()), Some(key))
+}
+
+// Gruppiert die Fahrten des Berichts; `worst_trips` begrenzt die Liste der schlechtesten Fahrten
+pub fn breakdown(report: &EvaluationReport, worst_trips: usize) -> Breakdown {
+    let mut rows = Vec::new();
+    for dimension in DIMENSIONS {
+        let mut groups: BTreeMap<String, BreakdownRow> = BTreeMap::new();
+        for trip in &report.trips {
+            let (reference_key, output_key) = keys(dimension, trip);
+            if let Some(key) = reference_key.filter(|_| trip.expected_vehicle.is_some()) {
+                let row = groups.entry(key).or_default();
+                row.reference_trips += 1;
+                row.reference_stops += trip.reference_stops;
+                row.within_tolerance_stops += trip.within_tolerance_stops;
+                if trip.is_correct() {
+                    row.correct_trips += 1;
+                }
+            }
+            if let Some(key) = output_key.filter(|_| trip.assigned_vehicle.is_some()) {
+                groups.entry(key).or_default().output_trips += 1;
+            }
+        }
+        let mut group_rows: Vec<BreakdownRow> = groups.into_iter().map(|(key, mut row)| {
+            row.dimension = dimension.to_string();
+            row.key = key;
+            row.precision = ratio(row.correct_trips, row.output_trips);
+            row.recall = ratio(row.correct_trips, row.reference_trips);
+            row.f1 = f1(row.precision, row.recall);
+            row.stop_share = ratio(row.within_tolerance_stops, row.reference_stops);
+            row
+        }).collect();
+        // Zahlen numerisch, andere Schlüssel dahinter alphabetisch
+        group_rows.sort_by(|a, b| numeric_key(&a.key).cmp(&numeric_key(&b.key)).then(a.key.cmp(&b.key)));
+        rows.extend(group_rows);
+    }
+
+    let mut confusion_counts: BTreeMap<(Option<&str>, Option<&str>), usize> = BTreeMap::new();
+    for trip in report.trips.iter().filter(|trip| !trip.is_correct()) {
+        *confusion_counts.entry((trip.expected_vehicle.as_deref(), trip.assigned_vehicle.as_deref())).or_insert(0) += 1;
+    }
+    let mut confusion: Vec<ConfusionPair> = confusion_counts.into_iter()
+        .map(|((expected, assigned), trips)| ConfusionPair {
+            expected_vehicle: expected.map(|vehicle| vehicle.to_string()),
+            assigned_vehicle: assigned.map(|vehicle| vehicle.to_string()),
+            trips,
+        })
+        .collect();
+    confusion.sort_by_key(|pair| std::cmp::Reverse(pair.trips));
+
+    // Nur Fahrten mit Fahrzeug in den Vergleichsdaten; falsch zugeordnete zuerst, dann nach dem Anteil richtiger Haltestellen
+    let mut worst: Vec<TripEvaluation> = report.trips.iter()
+        .filter(|trip| trip.expected_vehicle.is_some())
+        .cloned()
+        .collect();
+    worst.sort_by(|a, b| {
+        a.is_correct().cmp(&b.is_correct())
+            .then(a.stop_accuracy().total_cmp(&b.stop_accuracy()))
+            .then(a.trip_key.cmp(&b.trip_key))
+    });
+    worst.truncate(worst_trips);
+
+    Breakdown { rows, confusion, worst_trips: worst }
+}
+
+fn numeric_key(key: &str) -> (bool, u64) {
+    match key.parse::<u64>() {
+        Ok(number) => (false, number),
+        Err(_) => (true, 0),
+    }
+}
+
+// Gesamter Bericht eines Algorithmus für die JSON-Ausgabe
+#[derive(Serialize)]
+struct EvaluationExport<'a> {
+    algorithm: &'a str,
+    summary: &'a EvaluationReport,
+    breakdown: &'a [BreakdownRow],
+    confusion: &'a [ConfusionPair],
+    worst_trips: &'a [TripEvaluation],
+}
+
+// Schreibt die Aufschlüsselung eines Algorithmus in den Ordner `output_folder`:
+// {algorithmus}_aufschluesselung.csv, {algorithmus}_verwechslungen.csv, {algorithmus}_schlechteste_fahrten.csv
+// und {algorithmus}_auswertung.json
+pub fn write_breakdown(algorithm: &str, report: &EvaluationReport, breakdown: &Breakdown, output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let folder = Path::new(output_folder);
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_aufschluesselung.csv", algorithm)))?;
+    writer.write_record([
+        "MERKMAL", "WERT", "FAHRTEN_VERGLEICH", "FAHRTEN_ERGEBNIS", "FAHRTEN_RICHTIG", "PRECISION", "RECALL", "F1",
+        "HALTESTELLEN_VERGLEICH", "HALTESTELLEN_TOLERANZ", "ANTEIL_HALTESTELLEN",
+    ])?;
+    for row in &breakdown.rows {
+        writer.write_record([
+            row.dimension.clone(),
+            row.key.clone(),
+            row.reference_trips.to_string(),
+            row.output_trips.to_string(),
+            row.correct_trips.to_string(),
+            format!("{:.4}", row.precision),
+            format!("{:.4}", row.recall),
+            format!("{:.4}", row.f1),
+            row.reference_stops.to_string(),
+            row.within_tolerance_stops.to_string(),
+            format!("{:.4}", row.stop_share),
+        ])?;
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_verwechslungen.csv", algorithm)))?;
+    writer.write_record(["ERWARTETES_FAHRZEUG", "ZUGEORDNETES_FAHRZEUG", "FAHRTEN"])?;
+    for pair in &breakdown.confusion {
+        writer.write_record([
+            pair.expected_vehicle.as_deref().unwrap_or(""),
+            pair.assigned_vehicle.as_deref().unwrap_or(""),
+            pair.trips.to_string().as_str(),
+        ])?;
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_schlechteste_fahrten.csv", algorithm)))?;
+    writer.write_record([
+        "FAHRT", "LINIE", "RICHTUNG", "STUNDE", "ERWARTETES_FAHRZEUG", "ZUGEORDNETES_FAHRZEUG", "RICHTIG",
+        "HALTESTELLEN_VERGLEICH", "HALTESTELLEN_ERGEBNIS", "HALTESTELLEN_GLEICHES_FAHRZEUG", "HALTESTELLEN_TOLERANZ", "ANTEIL_HALTESTELLEN",
+    ])?;
+    for trip in &breakdown.worst_trips {
+        writer.write_record([
+            trip.trip_key.clone(),
+            trip.line.to_string(),
+            trip.richtung.to_string(),
+            trip.hour.map(|hour| hour.to_string()).unwrap_or_default(),
+            trip.expected_vehicle.clone().unwrap_or_default(),
+            trip.assigned_vehicle.clone().unwrap_or_default(),
+            (trip.is_correct() as u8).to_string(),
+            trip.reference_stops.to_string(),
+            trip.output_stops.to_string(),
+            trip.vehicle_correct_stops.to_string(),
+            trip.within_tolerance_stops.to_string(),
+            format!("{:.4}", trip.stop_accuracy()),
+        ])?;
+    }
+    writer.flush()?;
+
+    let export = EvaluationExport {
+        algorithm,
+        summary: report,
+        breakdown: &breakdown.rows,
+        confusion: &breakdown.confusion,
+        worst_trips: &breakdown.worst_trips,
+    };
+    let file = File::create(folder.join(format!("{}_auswertung.json", algorithm)))?;
+    serde_json::to_writer_pretty(file, &export)?;
+    Ok(())
+}
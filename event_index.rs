@@ -0,0 +1,115 @@
+// Räumlich-zeitlicher Index über alle Fahrzeugereignisse: ein Gitter aus Zellen (x, y in Metern) und Zeitfenstern,
+// damit nicht für jede Haltestelle alle Ereignisse durchsucht werden müssen
+use std::collections::HashMap;
+
+use crate::model::VehicleEvent;
+
+const EARTH_RADIUS_M: f64 = 6_371_000.0; // Radius der Erde in Metern
+
+#[derive(Debug)]
+pub struct EventIndex<'a> {
+    events: Vec<&'a VehicleEvent>,
+    cells: HashMap<(i64, i64, i64), Vec<usize>>, // (Zelle x, Zelle y, Zeitfenster) -> Positionen in `events`
+    cell_size_m: f64,
+    bucket_seconds: u64,
+    cos_reference_lat: f64, // Maßstab der Längengrade an der mittleren Breite der Ereignisse
+}
+
+impl<'a> EventIndex<'a> {
+    // Baut den Index einmal auf; die Zellgröße und die Länge der Zeitfenster sollten dem Suchradius
+    // und der Zeittoleranz der späteren Abfragen entsprechen
+    pub fn new(events: impl IntoIterator<Item = &'a VehicleEvent>, cell_size_m: f64, bucket_seconds: u64) -> Self {
+        let events: Vec<&VehicleEvent> = events.into_iter().collect();
+        let reference_lat = if events.is_empty() {
+            0.0
+        } else {
+            events.iter().map(|event| event.lat).sum::<f64>() / events.len() as f64
+        };
+
+        let mut index = EventIndex {
+            events,
+            cells: HashMap::new(),
+            cell_size_m: cell_size_m.max(1.0),
+            bucket_seconds: bucket_seconds.max(1),
+            cos_reference_lat: reference_lat.to_radians().cos(),
+        };
+        for position in 0..index.events.len() {
+            let event = index.events[position];
+            let key = index.cell(event.lat, event.lon, event.unixzeit);
+            index.cells.entry(key).or_default().push(position);
+        }
+        index
+    }
+
+    // Ebene Koordinaten in Metern (Projektion um die mittlere Breite)
+    fn project(&self, lat: f64, lon: f64) -> (f64, f64) {
+        (lon.to_radians() * EARTH_RADIUS_M * self.cos_reference_lat, lat.to_radians() * EARTH_RADIUS_M)
+    }
+
+    fn cell(&self, lat: f64, lon: f64, unixzeit: u64) -> (i64, i64, i64) {
+        let (x, y) = self.project(lat, lon);
+        (
+            (x / self.cell_size_m).floor() as i64,
+            (y / self.cell_size_m).floor() as i64,
+            (unixzeit / self.bucket_seconds) as i64,
+        )
+    }
+
+    // Alle Ereignisse höchstens `radius_m` Meter von der Position entfernt im Zeitfenster [from, to],
+    // in der Reihenfolge, in der sie dem Index übergeben wurden
+    pub fn query_window(&self, lat: f64, lon: f64, radius_m: f64, from: u64, to: u64) -> Vec<&'a VehicleEvent> {
+        let (x, y) = self.project(lat, lon);
+        // In Ost-West-Richtung entspricht der Radius abseits der mittleren Breite einer anderen Strecke in der Projektion
+        let x_radius = radius_m * self.cos_reference_lat / lat.to_radians().cos().max(1e-6);
+        let x_cells = ((x - x_radius) / self.cell_size_m).floor() as i64..=((x + x_radius) / self.cell_size_m).floor() as i64;
+        let y_cells = ((y - radius_m) / self.cell_size_m).floor() as i64..=((y + radius_m) / self.cell_size_m).floor() as i64;
+        let buckets = (from / self.bucket_seconds) as i64..=(to / self.bucket_seconds) as i64;
+
+        let mut positions = Vec::new();
+        for cell_x in x_cells {
+            for cell_y in y_cells.clone() {
+                for bucket in buckets.clone() {
+                    if let Some(cell) = self.cells.get(&(cell_x, cell_y, bucket)) {
+                        positions.extend(cell.iter().copied());
+                    }
+                }
+            }
+        }
+        positions.sort_unstable();
+
+        positions.into_iter()
+            .map(|position| self.events[position])
+            .filter(|event| from <= event.unixzeit && event.unixzeit <= to)
+            .filter(|event| haversine_m(lat, lon, event.lat, event.lon) <= radius_m)
+            .collect()
+    }
+}
+
+// Ereignisse eines Fahrzeugs in zeitlicher Reihenfolge für Abfragen nach Zeitfenstern
+#[derive(Debug)]
+pub struct EventTimeline<'a> {
+    events: Vec<&'a VehicleEvent>,
+}
+
+impl<'a> EventTimeline<'a> {
+    pub fn new(events: impl IntoIterator<Item = &'a VehicleEvent>) -> Self {
+        let mut events: Vec<&VehicleEvent> = events.into_iter().collect();
+        events.sort_by_key(|event| event.unixzeit);
+        EventTimeline { events }
+    }
+
+    // Alle Ereignisse im Zeitfenster [from, to]
+    pub fn between(&self, from: u64, to: u64) -> &[&'a VehicleEvent] {
+        let start = self.events.partition_point(|event| event.unixzeit < from);
+        let end = self.events.partition_point(|event| event.unixzeit <= to);
+        &self.events[start..end.max(start)]
+    }
+}
+
+// Entfernung zweier Koordinaten in Metern
+pub fn haversine_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    EARTH_RADIUS_M * 2.0 * a.sqrt().atan2((1.0 - a).sqrt())
+}
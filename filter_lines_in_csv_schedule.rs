@@ -1,64 +1,48 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::{self, BufRead};  
+
+use crate::model::{self, ScheduleStop};
+use crate::schema::InputOptions;
     
 // Eine Methode zum Filtern der Linien
-pub fn filter_lines_in_csv_schedule(csv_file_path: &str, output_folder: &str) -> Result<(), Box<dyn Error>> {
-    // Erstellen eines Ordners für die Ausgabedateien
-    let output_folder = output_folder;
-    fs::create_dir_all(output_folder)?;
-
+pub fn filter_lines_in_csv_schedule(csv_file_path: &str, output_folder: &str, options: &InputOptions) -> Result<(), Box<dyn Error>> {
     // CSV-Datei öffnen und lesen
-    let file = File::open(csv_file_path)?;
     println!("Öffnen und Lesen der Schedule-CSV-Datei: {}", csv_file_path);
-    let reader = io::BufReader::new(file);
-
-    // Vector zum Speichern der Zeilen jeder Linie
-    let mut lines_by_line: Vec<Vec<String>> = Vec::new();
+    let stops = model::read_schedule_stops_with(csv_file_path, options)?;
 
-    // Überschrift für die CSV-Datei
-    let mut header = String::new();
+    // Haltestellen nach Linien gruppieren und je Linie eine Datei schreiben
+    let lines_by_line = group_stops_by_line(stops);
+    write_lines_to_folder(output_folder, &lines_by_line)
+}
 
-    // Iteriere über jede Zeile in der CSV-Datei
-    for (index, line) in reader.lines().enumerate() {
-        let line_content = line?;
-        if index == 0 {
-            header = line_content.clone(); // Speichere die Überschrift
-            continue; // Überspringe die erste Zeile
-        }
-        let columns: Vec<&str> = line_content.split(';').collect();
+// Gruppiert die Haltestellen des Fahrplans nach der Liniennummer
+pub fn group_stops_by_line(stops: Vec<ScheduleStop>) -> BTreeMap<u32, Vec<ScheduleStop>> {
+    let mut lines_by_line: BTreeMap<u32, Vec<ScheduleStop>> = BTreeMap::new();
 
-        // Holen Sie den Wert der "line"-Spalte
-        let line_number: usize = columns[4].parse().unwrap(); // Annahme: Die Liniennummer ist in der fünften Spalte
+    // Fügen Sie jede Haltestelle zur entsprechenden Linie hinzu
+    for stop in stops {
+        lines_by_line.entry(stop.line).or_default().push(stop);
+    }
 
-        // Überprüfen, ob genügend Platz im Vektor vorhanden ist
-        while lines_by_line.len() <= line_number {
-            lines_by_line.push(Vec::new());
-        }
+    lines_by_line
+}
 
-        // Fügen Sie die Zeile zur entsprechenden Linie im Vector hinzu
-        lines_by_line[line_number].push(line_content);
-    }
+// Schreibt für jede Linie eine Datei line_<nummer>.csv
+pub fn write_lines_to_folder(output_folder: &str, lines_by_line: &BTreeMap<u32, Vec<ScheduleStop>>) -> Result<(), Box<dyn Error>> {
+    // Erstellen eines Ordners für die Ausgabedateien
+    fs::create_dir_all(output_folder)?;
 
     // Für jede Linie eine Datei erstellen
-    for (line_number, lines) in lines_by_line.iter().enumerate() {
-        // Überspringe Linien, die keine Zeilen haben
-        if lines.is_empty() {
-            continue;
-        }
-
+    for (line_number, lines) in lines_by_line {
         // Dateiname für die aktuelle Linie
         let filename = format!("{}/line_{}.csv", output_folder, line_number);
 
-        // Inhalt der Datei zusammenstellen
-        let file_content = format!("{}\n{}", header, lines.join("\n"));
-
         // Datei schreiben
-        fs::write(&filename, &file_content)?;
+        model::write_schedule_stops(&filename, lines)?;
 
         //println!("Datei erstellt: {}", filename);
     }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,514 @@
+// Konfliktfreie Gesamtzuordnung: kein Fahrzeug darf zwei Fahrten erhalten, die sich zeitlich überschneiden.
+// Die Fahrten eines Tages werden mit minimalen Gesamtkosten zugeordnet, jedes Fahrzeug fährt eine Kette von Fahrten
+// (exakte Suche je Konfliktgruppe, Startlösung mit der ungarischen Methode).
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::ops::Range;
+use csv::WriterBuilder;
+use rayon::prelude::*;
+
+use crate::matcher::MatchResult;
+use crate::model::ScheduleStop;
+use crate::trip_score::TripMatch;
+
+// Fahrt, die in der konfliktfreien Zuordnung kein Fahrzeug erhalten hat
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnassignedTrip {
+    pub trip_key: String,
+    pub reason: &'static str, // KEIN_KANDIDAT: kein Fahrzeug in Frage, KONFLIKT: alle Fahrzeuge bereits belegt
+}
+
+// Zeitraum, in dem das Fahrzeug die Fahrt fährt (erstes bis letztes zugeordnetes Ereignis)
+fn interval(trip_match: &TripMatch) -> Range<u64> {
+    let start = trip_match.stops.iter().map(|(_, event)| event.unixzeit).min().unwrap_or(0);
+    let end = trip_match.stops.iter().map(|(_, event)| event.unixzeit).max().unwrap_or(0);
+    start..end
+}
+
+// Echte Überschneidung; eine Fahrt darf am Ereignis beginnen, an dem die vorige endet (gleiche Endhaltestelle)
+fn overlaps(a: &Range<u64>, b: &Range<u64>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+// Ordnet die Fahrten konfliktfrei zu. `candidates` enthält je Fahrt die bewerteten Fahrzeuge. Ist `preferred`
+// angegeben (z.B. die Zuordnung der Umläufe), bleiben diese Paare erhalten, soweit sie keinen Konflikt verursachen.
+// Liefert außerdem die Zahl der Gruppen, deren Suche an MAX_SEARCH_STATES abgebrochen wurde.
+pub fn assign(trips: &HashMap<String, Vec<ScheduleStop>>, candidates: &BTreeMap<String, Vec<TripMatch>>, preferred: Option<&MatchResult>) -> (MatchResult, Vec<UnassignedTrip>, usize) {
+    let mut trip_keys: Vec<&String> = trips.keys().collect();
+    trip_keys.sort();
+
+    // Kandidaten je Fahrt; die bevorzugte Zuordnung ersetzt den Kandidaten mit demselben Fahrzeug
+    let options: Vec<Vec<(TripMatch, bool)>> = trip_keys.iter().map(|trip_key| {
+        let mut options: Vec<(TripMatch, bool)> = candidates.get(*trip_key).into_iter().flatten()
+            .map(|trip_match| (trip_match.clone(), false))
+            .collect();
+        if let Some(chosen) = preferred.and_then(|result| result.trips.get(*trip_key)) {
+            options.retain(|(trip_match, _)| trip_match.vehicle != chosen.vehicle);
+            options.insert(0, (chosen.clone(), true));
+        }
+        options
+    }).collect();
+
+    // Fahrten, die über ein gemeinsames Fahrzeug in Konflikt stehen, bilden eine Gruppe; die Gruppen sind
+    // voneinander unabhängig und werden parallel gelöst
+    let groups = conflict_groups(&options);
+    let solved: Vec<(Vec<(usize, usize)>, bool)> = groups.par_iter().map(|group| solve_group(group, &options)).collect();
+
+    let mut chosen: Vec<Option<usize>> = vec![None; trip_keys.len()];
+    let mut incomplete = 0;
+    for (assigned, complete) in solved {
+        incomplete += usize::from(!complete);
+        for (trip, option) in assigned {
+            chosen[trip] = Some(option);
+        }
+    }
+
+    let mut result = MatchResult::default();
+    let mut unassigned = Vec::new();
+    for (trip, trip_key) in trip_keys.iter().enumerate() {
+        match chosen[trip] {
+            Some(option) => result.insert(options[trip][option].0.clone()),
+            None => unassigned.push(UnassignedTrip {
+                trip_key: trip_key.to_string(),
+                reason: if options[trip].is_empty() { "KEIN_KANDIDAT" } else { "KONFLIKT" },
+            }),
+        }
+    }
+    (result, unassigned, incomplete)
+}
+
+// Zerlegt die Fahrten in Gruppen, die über sich überschneidende Kandidaten desselben Fahrzeugs verbunden sind
+fn conflict_groups(options: &[Vec<(TripMatch, bool)>]) -> Vec<Vec<usize>> {
+    let mut parent: Vec<usize> = (0..options.len()).collect();
+    fn find(parent: &mut [usize], mut node: usize) -> usize {
+        while parent[node] != node {
+            parent[node] = parent[parent[node]];
+            node = parent[node];
+        }
+        node
+    }
+
+    // Je Fahrzeug die Zeiträume aller Kandidaten, nach Beginn sortiert
+    let mut by_vehicle: BTreeMap<&str, Vec<(Range<u64>, usize)>> = BTreeMap::new();
+    for (trip, trip_options) in options.iter().enumerate() {
+        for (trip_match, _) in trip_options {
+            by_vehicle.entry(trip_match.vehicle.as_str()).or_default().push((interval(trip_match), trip));
+        }
+    }
+    for intervals in by_vehicle.values_mut() {
+        intervals.sort_by_key(|(range, trip)| (range.start, range.end, *trip));
+        // Jeder Zeitraum wird mit allen noch laufenden Zeiträumen verbunden
+        let mut active: Vec<(Range<u64>, usize)> = Vec::new();
+        for (range, trip) in intervals.iter() {
+            active.retain(|(other, _)| other.end > range.start);
+            for (other, other_trip) in &active {
+                if overlaps(other, range) {
+                    let (a, b) = (find(&mut parent, *other_trip), find(&mut parent, *trip));
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+            active.push((range.clone(), *trip));
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (trip, trip_options) in options.iter().enumerate() {
+        if !trip_options.is_empty() {
+            let root = find(&mut parent, trip);
+            groups.entry(root).or_default().push(trip);
+        }
+    }
+    groups.into_values().collect()
+}
+
+// Größte Zahl untersuchter Zustände der exakten Suche je Gruppe; darüber bleibt die beste bis dahin gefundene Lösung
+const MAX_SEARCH_STATES: usize = 1_000_000;
+
+// Löst eine Gruppe mit minimalen Gesamtkosten: jedes Fahrzeug fährt eine Kette sich nicht überschneidender Fahrten.
+// Startlösung sind die Runden der ungarischen Methode, danach werden alle Kombinationen per Branch and Bound
+// durchsucht. Liefert (Fahrt, gewählter Kandidat) und ob die Suche vollständig war.
+fn solve_group(group: &[usize], options: &[Vec<(TripMatch, bool)>]) -> (Vec<(usize, usize)>, bool) {
+    // Gewicht einer nicht abgedeckten Haltestelle: größer als die Summe aller Abweichungsanteile der Gruppe
+    let weight = group.len() as f64 + 1.0;
+
+    // Fahrten nach dem frühesten Beginn ihrer Kandidaten, Kandidaten je Fahrt nach Kosten sortiert
+    let mut trips: Vec<SearchTrip> = group.iter().map(|&trip| {
+        let mut candidates: Vec<(f64, usize, Range<u64>)> = options[trip].iter().enumerate()
+            .map(|(option, (trip_match, is_preferred))| (pair_cost(trip_match, *is_preferred, weight), option, interval(trip_match)))
+            .collect();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        SearchTrip { trip, candidates, unassigned_cost: unassigned_cost(&options[trip], weight) }
+    }).collect();
+    trips.sort_by_key(|trip| (trip.candidates.iter().map(|(_, _, range)| range.start).min(), trip.trip));
+
+    // Untere Schranke der offenen Fahrten ab Position i: je Fahrt die geringsten Kosten ohne Rücksicht auf Konflikte
+    let mut bounds = vec![0.0; trips.len() + 1];
+    for position in (0..trips.len()).rev() {
+        let trip = &trips[position];
+        let cheapest = trip.candidates.first().map_or(trip.unassigned_cost, |(cost, _, _)| cost.min(trip.unassigned_cost));
+        bounds[position] = bounds[position + 1] + cheapest;
+    }
+
+    let initial = solve_rounds(group, options, weight);
+    let mut search = GroupSearch {
+        options,
+        best: trips.iter().map(|trip| initial.iter().find(|(other, _)| *other == trip.trip).map(|(_, option)| *option)).collect(),
+        best_cost: 0.0,
+        trips,
+        bounds,
+        chosen: Vec::new(),
+        occupied: Vec::new(),
+        states: 0,
+    };
+    search.best_cost = search.total_cost(&search.best);
+    search.chosen = vec![None; search.trips.len()];
+    search.search(0, 0.0);
+
+    let complete = search.states <= MAX_SEARCH_STATES;
+    let assigned = search.trips.iter().zip(&search.best)
+        .filter_map(|(trip, option)| option.map(|option| (trip.trip, option)))
+        .collect();
+    (assigned, complete)
+}
+
+// Fahrt einer Gruppe in der exakten Suche: (Kosten, Kandidat, Zeitraum) je Kandidat
+struct SearchTrip {
+    trip: usize,
+    candidates: Vec<(f64, usize, Range<u64>)>,
+    unassigned_cost: f64,
+}
+
+// Zustand der exakten Suche einer Gruppe
+struct GroupSearch<'a> {
+    options: &'a [Vec<(TripMatch, bool)>],
+    trips: Vec<SearchTrip>,
+    bounds: Vec<f64>,
+    chosen: Vec<Option<usize>>,          // Gewählter Kandidat je Position, None = nicht zugeordnet
+    occupied: Vec<(&'a str, Range<u64>)>, // Zeiträume der gewählten Kandidaten
+    best: Vec<Option<usize>>,
+    best_cost: f64,
+    states: usize,
+}
+
+impl<'a> GroupSearch<'a> {
+    fn total_cost(&self, chosen: &[Option<usize>]) -> f64 {
+        self.trips.iter().zip(chosen).map(|(trip, option)| match option {
+            Some(option) => trip.candidates.iter().find(|(_, candidate, _)| candidate == option).map_or(f64::INFINITY, |(cost, _, _)| *cost),
+            None => trip.unassigned_cost,
+        }).sum()
+    }
+
+    fn search(&mut self, position: usize, cost: f64) {
+        self.states += 1;
+        if self.states > MAX_SEARCH_STATES || cost + self.bounds[position] >= self.best_cost - 1e-9 {
+            return;
+        }
+        if position == self.trips.len() {
+            self.best_cost = cost;
+            self.best = self.chosen.clone();
+            return;
+        }
+        for index in 0..self.trips[position].candidates.len() {
+            let (pair_cost, option, range) = self.trips[position].candidates[index].clone();
+            let vehicle = self.options[self.trips[position].trip][option].0.vehicle.as_str();
+            if self.occupied.iter().any(|(other, other_range)| *other == vehicle && overlaps(other_range, &range)) {
+                continue;
+            }
+            self.occupied.push((vehicle, range));
+            self.chosen[position] = Some(option);
+            self.search(position + 1, cost + pair_cost);
+            self.occupied.pop();
+        }
+        self.chosen[position] = None;
+        self.search(position + 1, cost + self.trips[position].unassigned_cost);
+    }
+}
+
+// Kosten einer Fahrt ohne Fahrzeug: teurer als jeder Kandidat, auch ohne abgedeckte Haltestelle
+fn unassigned_cost(options: &[(TripMatch, bool)], weight: f64) -> f64 {
+    options.first().map_or(0, |(trip_match, _)| trip_match.score.stops_total) as f64 * weight + weight
+}
+
+// Startlösung einer Gruppe in Runden: in jeder Runde erhält jedes Fahrzeug höchstens eine Fahrt (ungarische Methode),
+// spätere Runden vergeben nur noch Fahrzeuge, die zu den bereits festgelegten Fahrten keinen Konflikt haben.
+// Liefert (Fahrt, gewählter Kandidat).
+fn solve_rounds(group: &[usize], options: &[Vec<(TripMatch, bool)>], weight: f64) -> Vec<(usize, usize)> {
+    let mut assigned: Vec<(usize, usize)> = Vec::new();
+    let mut occupied: HashMap<&str, Vec<Range<u64>>> = HashMap::new();
+    let mut remaining: Vec<usize> = group.to_vec();
+
+    loop {
+        // Noch zulässige Kandidaten der offenen Fahrten
+        let feasible: Vec<(usize, Vec<usize>)> = remaining.iter()
+            .map(|&trip| {
+                let free: Vec<usize> = (0..options[trip].len())
+                    .filter(|&option| {
+                        let trip_match = &options[trip][option].0;
+                        let range = interval(trip_match);
+                        occupied.get(trip_match.vehicle.as_str())
+                            .is_none_or(|ranges| ranges.iter().all(|other| !overlaps(other, &range)))
+                    })
+                    .collect();
+                (trip, free)
+            })
+            .filter(|(_, free)| !free.is_empty())
+            .collect();
+        if feasible.is_empty() {
+            break;
+        }
+
+        // Spalten: die Fahrzeuge der Runde, danach je Fahrt eine Spalte "nicht zugeordnet"
+        let mut vehicles: Vec<&str> = feasible.iter()
+            .flat_map(|(trip, free)| free.iter().map(move |&option| options[*trip][option].0.vehicle.as_str()))
+            .collect();
+        vehicles.sort_unstable();
+        vehicles.dedup();
+        let columns = vehicles.len() + feasible.len();
+
+        let mut costs = vec![vec![f64::INFINITY; columns]; feasible.len()];
+        for (row, (trip, free)) in feasible.iter().enumerate() {
+            for &option in free {
+                let (trip_match, is_preferred) = &options[*trip][option];
+                let column = vehicles.binary_search(&trip_match.vehicle.as_str()).unwrap();
+                costs[row][column] = pair_cost(trip_match, *is_preferred, weight);
+            }
+            costs[row][vehicles.len() + row] = unassigned_cost(&options[*trip], weight);
+        }
+
+        let mut progress = false;
+        for (row, column) in hungarian(&costs).into_iter().enumerate() {
+            if column >= vehicles.len() {
+                continue;
+            }
+            let (trip, free) = &feasible[row];
+            let option = free.iter().copied()
+                .find(|&option| options[*trip][option].0.vehicle == vehicles[column])
+                .unwrap();
+            let trip_match = &options[*trip][option].0;
+            occupied.entry(trip_match.vehicle.as_str()).or_default().push(interval(trip_match));
+            assigned.push((*trip, option));
+            remaining.retain(|other| other != trip);
+            progress = true;
+        }
+        if !progress {
+            break;
+        }
+    }
+    assigned
+}
+
+// Kosten eines Paares: nicht abgedeckte Haltestellen zählen vor den Abweichungen (Anteil unter 1 je Fahrt);
+// bevorzugte Paare kosten nichts
+fn pair_cost(trip_match: &TripMatch, is_preferred: bool, weight: f64) -> f64 {
+    if is_preferred {
+        return 0.0;
+    }
+    let score = &trip_match.score;
+    let uncovered = score.stops_total.saturating_sub(score.stops_covered) as f64;
+    uncovered * weight + score.cost / (score.cost + 1.0)
+}
+
+// Ungarische Methode (mit Potentialen) für eine Matrix mit höchstens so vielen Zeilen wie Spalten;
+// liefert je Zeile die gewählte Spalte. Unzulässige Paare haben die Kosten unendlich.
+fn hungarian(costs: &[Vec<f64>]) -> Vec<usize> {
+    let rows = costs.len();
+    let columns = costs.first().map_or(0, |row| row.len());
+    // Unzulässige Paare werden durch einen endlichen Wert ersetzt, der jede zulässige Lösung übersteigt
+    let finite_max = costs.iter().flatten().copied().filter(|cost| cost.is_finite()).fold(0.0, f64::max);
+    let forbidden = (finite_max + 1.0) * (rows as f64 + 1.0);
+    let cost = |row: usize, column: usize| {
+        let value = costs[row - 1][column - 1];
+        if value.is_finite() { value } else { forbidden }
+    };
+
+    let mut u = vec![0.0; rows + 1];
+    let mut v = vec![0.0; columns + 1];
+    let mut matched_row = vec![0usize; columns + 1]; // Spalte -> Zeile (0 = frei)
+    let mut way = vec![0usize; columns + 1];
+
+    for row in 1..=rows {
+        matched_row[0] = row;
+        let mut column0 = 0;
+        let mut min_value = vec![f64::INFINITY; columns + 1];
+        let mut used = vec![false; columns + 1];
+        loop {
+            used[column0] = true;
+            let row0 = matched_row[column0];
+            let mut delta = f64::INFINITY;
+            let mut column1 = 0;
+            for column in 1..=columns {
+                if !used[column] {
+                    let current = cost(row0, column) - u[row0] - v[column];
+                    if current < min_value[column] {
+                        min_value[column] = current;
+                        way[column] = column0;
+                    }
+                    if min_value[column] < delta {
+                        delta = min_value[column];
+                        column1 = column;
+                    }
+                }
+            }
+            for column in 0..=columns {
+                if used[column] {
+                    u[matched_row[column]] += delta;
+                    v[column] -= delta;
+                } else {
+                    min_value[column] -= delta;
+                }
+            }
+            column0 = column1;
+            if matched_row[column0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let column1 = way[column0];
+            matched_row[column0] = matched_row[column1];
+            column0 = column1;
+            if column0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![0; rows];
+    for column in 1..=columns {
+        if matched_row[column] != 0 {
+            assignment[matched_row[column] - 1] = column - 1;
+        }
+    }
+    assignment
+}
+
+// Schreibt die nicht zugeordneten Fahrten aller Algorithmen in eine CSV-Datei
+pub fn write_unassigned(unassigned: &[(String, UnassignedTrip)], output_file: &str) -> Result<(), Box<dyn Error>> {
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(output_file)?;
+    writer.write_record(["ALGORITHMUS", "FAHRT", "GRUND"])?;
+    for (algorithm, trip) in unassigned {
+        writer.write_record([algorithm.as_str(), trip.trip_key.as_str(), trip.reason])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::VehicleEvent;
+    use crate::timestamp::Timestamp;
+    use crate::trip_score::TripScore;
+
+    fn stop(schedule_id: &str, zeit: u64) -> ScheduleStop {
+        ScheduleStop {
+            schedule_id: schedule_id.to_string(),
+            datum: "2023-10-25".to_string(),
+            frt_fid: 0,
+            frt_start: 0,
+            line: 1,
+            richtung: 1,
+            varianten: 1,
+            umlauf: 1,
+            lfnr: 1,
+            ankunft: 0,
+            abfahrt: 0,
+            zeitpkt: String::new(),
+            zeit,
+            ort_nr: 1,
+            ort_name: String::new(),
+            lon: 0.0,
+            lat: 0.0,
+            x: 0.0,
+            y: 0.0,
+            fahrt_start: String::new(),
+            fahrt_ende: String::new(),
+            wkt: String::new(),
+        }
+    }
+
+    fn event(vehicle: &str, unixzeit: u64) -> VehicleEvent {
+        VehicleEvent {
+            vehicle: vehicle.to_string(),
+            datum: "2023-10-25".to_string(),
+            zeit: Timestamp(unixzeit as i64),
+            zeit_next: None,
+            unixzeit,
+            lat: 0.0,
+            lon: 0.0,
+            x: 0.0,
+            y: 0.0,
+            typ: String::new(),
+            einsteiger: 0,
+            aussteiger: 0,
+            wkt: String::new(),
+        }
+    }
+
+    // Fahrt `trip_key` mit dem Fahrzeug `vehicle` von `start` bis `end` (Minuten nach Mitternacht), alle Haltestellen abgedeckt
+    fn candidate(trip_key: &str, vehicle: &str, start: u64, end: u64, cost: f64) -> TripMatch {
+        let stops = vec![
+            (stop(&format!("{}_1", trip_key), start * 60), event(vehicle, start * 60)),
+            (stop(&format!("{}_2", trip_key), end * 60), event(vehicle, end * 60)),
+        ];
+        let score = TripScore { stops_total: 2, stops_covered: 2, cost, ..Default::default() };
+        TripMatch { trip_key: trip_key.to_string(), vehicle: vehicle.to_string(), stops, score }
+    }
+
+    fn solve(matches: Vec<TripMatch>) -> (MatchResult, Vec<UnassignedTrip>, usize) {
+        let mut trips: HashMap<String, Vec<ScheduleStop>> = HashMap::new();
+        let mut candidates: BTreeMap<String, Vec<TripMatch>> = BTreeMap::new();
+        for trip_match in matches {
+            trips.entry(trip_match.trip_key.clone()).or_insert_with(|| trip_match.stops.iter().map(|(stop, _)| stop.clone()).collect());
+            candidates.entry(trip_match.trip_key.clone()).or_default().push(trip_match);
+        }
+        assign(&trips, &candidates, None)
+    }
+
+    #[test]
+    fn hungarian_finds_minimum_assignment() {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        // 1 + 2 + 2 = 5 ist das Minimum
+        assert_eq!(hungarian(&costs), vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn hungarian_avoids_forbidden_pairs_with_more_columns_than_rows() {
+        let costs = vec![
+            vec![1.0, f64::INFINITY, 5.0],
+            vec![0.5, 3.0, f64::INFINITY],
+        ];
+        // 1 + 3 = 4 statt 5 + 0.5
+        assert_eq!(hungarian(&costs), vec![0, 1]);
+    }
+
+    #[test]
+    fn one_vehicle_covers_the_longest_chain_instead_of_the_cheapest_trip() {
+        // A 08:00-09:00, B 08:30-09:30, C 09:15-10:00: B überschneidet sich mit A und C, A und C passen hintereinander
+        let (result, unassigned, incomplete) = solve(vec![
+            candidate("A", "V", 8 * 60, 9 * 60, 0.1),
+            candidate("B", "V", 8 * 60 + 30, 9 * 60 + 30, 0.05),
+            candidate("C", "V", 9 * 60 + 15, 10 * 60, 0.1),
+        ]);
+        assert_eq!(result.vehicle("A"), Some("V"));
+        assert_eq!(result.vehicle("C"), Some("V"));
+        assert_eq!(result.vehicle("B"), None);
+        assert_eq!(unassigned, vec![UnassignedTrip { trip_key: "B".to_string(), reason: "KONFLIKT" }]);
+        assert_eq!(incomplete, 0);
+    }
+
+    #[test]
+    fn overlapping_trips_use_a_second_vehicle_when_available() {
+        let (result, unassigned, _) = solve(vec![
+            candidate("A", "V", 8 * 60, 9 * 60, 0.1),
+            candidate("B", "V", 8 * 60 + 30, 9 * 60 + 30, 0.05),
+            candidate("B", "W", 8 * 60 + 30, 9 * 60 + 30, 0.2),
+        ]);
+        assert_eq!(result.vehicle("A"), Some("V"));
+        assert_eq!(result.vehicle("B"), Some("W"));
+        assert!(unassigned.is_empty());
+    }
+}
@@ -0,0 +1,63 @@
+// Kostenfunktion der Zuordnung: bewertet ein Ereignis als Kandidat für eine Haltestelle und, summiert über die
+// Haltestellen, ein Fahrzeug für eine Fahrt (TripScore, beide Algorithmen). Die gewichtete Funktion normiert Entfernung
+// und Zeitabweichung auf die Toleranzen; die bisherige Formel (Produkt) ist als Alternative wählbar.
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::config::Tolerances;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CostFunction {
+    // Gewichtete Summe aus Entfernung / candidate_radius_m und Zeitabweichung / time_tolerance_seconds
+    Weighted,
+    // Produkt aus Entfernung und Zeitabweichung (bisherige Formel)
+    Legacy,
+}
+
+// Einstellungen der Kostenfunktion ([heuristic] in der Konfiguration)
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct HeuristicCost {
+    pub cost_function: CostFunction,
+    pub spatial_weight: f64,                      // Gewicht der normierten Entfernung
+    pub temporal_weight: f64,                     // Gewicht der normierten Zeitabweichung
+    pub max_distance_m: Option<f64>,              // Harte Grenze der Entfernung, darüber ist das Ereignis kein Kandidat
+    pub max_time_difference_seconds: Option<u64>, // Harte Grenze der Zeitabweichung zur erwarteten Zeit
+}
+
+impl Default for HeuristicCost {
+    fn default() -> Self {
+        HeuristicCost {
+            cost_function: CostFunction::Weighted,
+            spatial_weight: 1.0,
+            temporal_weight: 1.0,
+            max_distance_m: None,
+            max_time_difference_seconds: None,
+        }
+    }
+}
+
+impl HeuristicCost {
+    // Kosten eines Ereignisses in `distance_m` Metern und `time_difference_s` Sekunden Abstand zur erwarteten Zeit;
+    // None, wenn eine harte Grenze überschritten ist. Kleinere Kosten sind besser.
+    pub fn cost(&self, distance_m: f64, time_difference_s: u64, tolerances: &Tolerances) -> Option<f64> {
+        if self.max_distance_m.is_some_and(|limit| distance_m > limit)
+            || self.max_time_difference_seconds.is_some_and(|limit| time_difference_s > limit)
+        {
+            return None;
+        }
+        Some(self.pair_cost(distance_m, time_difference_s, tolerances))
+    }
+
+    // Kosten eines Paares Haltestelle -> Ereignis nach der Kostenfunktion, ohne die harten Grenzen
+    pub fn pair_cost(&self, distance_m: f64, time_difference_s: u64, tolerances: &Tolerances) -> f64 {
+        match self.cost_function {
+            CostFunction::Weighted => {
+                self.spatial_weight * distance_m / tolerances.candidate_radius_m.max(1.0)
+                    + self.temporal_weight * time_difference_s as f64 / tolerances.time_tolerance_seconds.max(1) as f64
+            }
+            CostFunction::Legacy => distance_m * time_difference_s as f64,
+        }
+    }
+}
@@ -1,102 +1,75 @@
 // Importieren der benötigten Standardbibliotheksmodule und externen Bibliotheken
-use std::fs::{self, File}; // Modul für Dateioperationen
-use std::io::{BufReader, BufWriter, Write}; // Modul für Ein- und Ausgabe
+use std::collections::HashMap; // HashMap für die Fahrten, Schlüssel ist der Dateiname ohne Endung
+use std::fs; // Modul für Dateioperationen
 use std::error::Error; // Trait für Fehlerbehandlung
-use csv::{ReaderBuilder, WriterBuilder}; // Externe CSV-Bibliothek für das Lesen und Schreiben von CSV-Dateien
 
-pub fn run(csv_file_path: &str, output_folder: &str) -> Result<(), Box<dyn Error>> {
-    let input_file = File::open(csv_file_path)?;
-    let reader = BufReader::new(input_file);
-    let mut csv_reader = ReaderBuilder::new()
-        .delimiter(b';')
-        .from_reader(reader);
+use crate::model::{self, ScheduleStop}; // Gemeinsames Datenmodell für das Lesen und Schreiben der CSV-Dateien
+use crate::schema::InputOptions; // Spaltenzuordnung und Fehlerbehandlung
 
-    let mut current_section: Vec<String> = Vec::new();
-    let mut section_identifiers: Option<(String, String, String, String, String)> = None;
+pub fn run(csv_file_path: &str, output_folder: &str, options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    let stops = model::read_schedule_stops_with(csv_file_path, options)?;
+    let trips = split_into_trips(stops);
+    write_trips_to_folder(output_folder, &trips)
+}
 
-    for result in csv_reader.records() {
-        let record = result?;
-        let lfnr = record[8].parse::<usize>()?;
+// Teilt den Fahrplan an jeder Haltestelle mit lfnr 1 in einzelne Fahrten.
+// Schlüssel ist line_direction_variant_umlauf_fahrtstart (wie der Dateiname der Fahrt)
+pub fn split_into_trips(stops: Vec<ScheduleStop>) -> HashMap<String, Vec<ScheduleStop>> {
+    let mut trips: HashMap<String, Vec<ScheduleStop>> = HashMap::new();
+    let mut current_section: Vec<ScheduleStop> = Vec::new();
+    let mut section_key: Option<String> = None;
 
+    for stop in stops {
         // Wenn lfnr != 1, füge Zeile zur aktuellen Sektion hinzu
-        if lfnr != 1 {
-            current_section.push(record.into_iter().collect::<Vec<&str>>().join(";"));
+        if stop.lfnr != 1 {
+            current_section.push(stop);
         } else {
             // Wenn lfnr == 1 und aktuelle Sektion nicht leer ist
             if !current_section.is_empty() {
-                // Überprüfe, ob bereits eine Datei für diese Sektion existiert
-                if let Some(section_identifiers) = &section_identifiers {
-                    let file_exists = check_file_exists(output_folder, &section_identifiers)?;
-                    if !file_exists {
-                        // Datei erstellen und alle Zeilen in der Liste hinzufügen
-                        write_section_to_file(output_folder, &current_section, &section_identifiers)?;
-                        current_section.clear();
+                // Überprüfe, ob bereits eine Fahrt für diese Sektion existiert
+                if let Some(key) = &section_key {
+                    if !trips.contains_key(key) {
+                        // Fahrt mit allen Zeilen der Liste anlegen
+                        trips.insert(key.clone(), std::mem::take(&mut current_section));
                     }
                 }
             }
 
-            // Füge Zeile mit lfnr 1 zur aktuellen Sektion hinzu
-            current_section.push(record.into_iter().collect::<Vec<&str>>().join(";"));
+            // Setze den Schlüssel auf die Werte der aktuellen Zeile
+            section_key = Some(trip_key(&stop));
 
-            // Setze section_identifiers auf die Werte der aktuellen Zeile
-            let line = record[4].to_string();
-            let direction = record[5].to_string();
-            let variant = record[6].to_string();
-            let umlauf = record[7].to_string();
-            let fahrtstart = record[19].to_string();
-            section_identifiers = Some((line, direction, variant, umlauf, fahrtstart));
+            // Füge Zeile mit lfnr 1 zur aktuellen Sektion hinzu
+            current_section.push(stop);
         }
     }
 
-    // Schreiben der letzten Sektion, falls vorhanden
+    // Übernehmen der letzten Sektion, falls vorhanden
     if !current_section.is_empty() {
-        if let Some(section_identifiers) = &section_identifiers {
-            write_section_to_file(output_folder, &current_section, &section_identifiers)?;
+        if let Some(key) = section_key {
+            trips.insert(key, current_section);
         }
     }
 
-    Ok(())
+    trips
 }
 
-// Funktion zum Schreiben einer Sektion in eine separate CSV-Datei
-fn write_section_to_file(output_folder: &str, section: &[String], section_identifiers: &(String, String, String, String, String)) -> Result<(), Box<dyn Error>> {
-    // Extrahieren der Sektionsidentifikatoren
-    let (line, direction, variant, umlauf, fahrtstart) = section_identifiers;
+// Schlüssel einer Fahrt aus Linie, Richtung, Variante, Umlauf und Fahrtstart
+pub fn trip_key(stop: &ScheduleStop) -> String {
+    format!("{}_{}_{}_{}_{}", stop.line, stop.richtung, stop.varianten, stop.umlauf, stop.fahrt_start)
+}
+
+// Funktion zum Schreiben jeder Fahrt in eine separate CSV-Datei
+pub fn write_trips_to_folder(output_folder: &str, trips: &HashMap<String, Vec<ScheduleStop>>) -> Result<(), Box<dyn Error>> {
     let output_dir = output_folder; // Ordner für die Ausgabedateien
     fs::create_dir_all(output_dir)?; // Erstellen des Ausgabeverzeichnisses, falls es nicht existiert
 
-    // Dateiname basierend auf den Werten von line, direction, variant und umlauf erstellen
-    let filename = format!("{}_{}_{}_{}_{}.csv", line, direction, variant, umlauf, fahrtstart);
-    let output_file_path = format!("{}/{}", output_dir, filename);
-
-    // Öffnen der Ausgabedatei
-    let output_file = File::create(output_file_path)?;
-    let mut writer = BufWriter::new(output_file);
-    let mut csv_writer = WriterBuilder::new()
-        .delimiter(b';') // Festlegen des Trennzeichens für die CSV-Datei
-        .from_writer(writer);
+    for (key, section) in trips {
+        // Dateiname basierend auf den Werten von line, direction, variant und umlauf erstellen
+        let output_file_path = format!("{}/{}.csv", output_dir, key);
 
-    // Schreiben aller Zeilen der Sektion in die Datei
-    for line in section {
-        csv_writer.write_record(line.split(';'))?;
+        // Schreiben aller Zeilen der Sektion in die Datei
+        model::write_schedule_stops(&output_file_path, section)?;
     }
 
-    csv_writer.flush()?; // Sicherstellen, dass alle Daten in die Datei geschrieben werden
     Ok(()) // Erfolgreicher Abschluss der Funktion
 }
-
-// Funktion zum Überprüfen, ob eine Datei für eine Sektion bereits existiert
-fn check_file_exists(output_folder: &str, section_identifiers: &(String, String, String, String, String)) -> Result<bool, Box<dyn Error>> {
-    // Extrahieren der Sektionsidentifikatoren
-    let (line, direction, variant, umlauf, fahrtstart) = section_identifiers;
-    let output_dir = output_folder; // Ordner für die Ausgabedateien
-    let filename = format!("{}_{}_{}_{}_{}.csv", line, direction, variant, umlauf, fahrtstart);
-    let file_path = format!("{}/{}", output_dir, filename);
-
-    // Überprüfen, ob die Datei existiert
-    Ok(std::path::Path::new(&file_path).exists())
-}
-
-
-
-
@@ -0,0 +1,143 @@
+// Besetzungsverlauf je Fahrt: aus Einsteigern und Aussteigern der zugeordneten Haltestellen (nach LFD_NR) ergibt sich
+// die Besetzung auf jedem Abschnitt. Auffällig sind Fahrten, deren Besetzung negativ wird oder am Ende nicht auf 0 fällt.
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use csv::WriterBuilder;
+
+use crate::matcher::MatchedTrips;
+use crate::model::{ScheduleStop, VehicleEvent};
+
+// Besetzung nach einer Haltestelle, also auf dem Abschnitt bis zur nächsten
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadPoint {
+    pub schedule_id: String,
+    pub lfnr: u32,
+    pub ort_nr: u32,
+    pub ort_name: String,
+    pub einsteiger: u32,
+    pub aussteiger: u32,
+    pub load: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadProfile {
+    pub trip_key: String,
+    pub vehicle: String,
+    pub line: u32,
+    pub richtung: u32,
+    pub points: Vec<LoadPoint>,
+    pub max_load: i64,                 // Höchste Besetzung auf einem Abschnitt
+    pub max_load_index: Option<usize>, // Haltestelle (Index in `points`), nach der die höchste Besetzung erreicht wird
+    pub min_load: i64,                 // Niedrigste Besetzung nach einer Haltestelle
+    pub final_load: i64,               // Besetzung nach der letzten Haltestelle
+}
+
+impl LoadProfile {
+    // Es steigen mehr Fahrgäste aus, als eingestiegen sind
+    pub fn has_negative_load(&self) -> bool {
+        self.min_load < 0
+    }
+
+    // Nach der letzten Haltestelle sind noch (oder zu wenige) Fahrgäste im Fahrzeug
+    pub fn is_unbalanced(&self) -> bool {
+        self.final_load != 0
+    }
+
+    // Abschnitt mit der höchsten Besetzung
+    pub fn max_load_point(&self) -> Option<&LoadPoint> {
+        self.max_load_index.map(|index| &self.points[index])
+    }
+}
+
+// Besetzungsverlauf einer Fahrt; die Haltestellen müssen nach LFD_NR sortiert sein
+pub fn load_profile(trip_key: &str, stops: &[(ScheduleStop, VehicleEvent)]) -> LoadProfile {
+    let mut profile = LoadProfile {
+        trip_key: trip_key.to_string(),
+        vehicle: stops.first().map(|(_, event)| event.vehicle.clone()).unwrap_or_default(),
+        line: stops.first().map_or(0, |(stop, _)| stop.line),
+        richtung: stops.first().map_or(0, |(stop, _)| stop.richtung),
+        points: Vec::with_capacity(stops.len()),
+        max_load: 0,
+        max_load_index: None,
+        min_load: 0,
+        final_load: 0,
+    };
+
+    let mut load: i64 = 0;
+    for (index, (stop, event)) in stops.iter().enumerate() {
+        load += event.einsteiger as i64 - event.aussteiger as i64;
+        if profile.max_load_index.is_none() || load > profile.max_load {
+            profile.max_load = load;
+            profile.max_load_index = Some(index);
+        }
+        profile.min_load = profile.min_load.min(load);
+        profile.points.push(LoadPoint {
+            schedule_id: stop.schedule_id.clone(),
+            lfnr: stop.lfnr,
+            ort_nr: stop.ort_nr,
+            ort_name: stop.ort_name.clone(),
+            einsteiger: event.einsteiger,
+            aussteiger: event.aussteiger,
+            load,
+        });
+    }
+    profile.final_load = load;
+    profile
+}
+
+// Besetzungsverläufe aller zugeordneten Fahrten
+pub fn load_profiles(trips: &MatchedTrips) -> Vec<LoadProfile> {
+    trips.iter().map(|(trip_key, stops)| load_profile(trip_key, stops)).collect()
+}
+
+// Schreibt die Besetzung je Haltestelle ({algorithmus}_besetzung.csv) und je Fahrt mit den Auffälligkeiten
+// ({algorithmus}_besetzung_fahrten.csv) in den Ordner `output_folder`
+pub fn write_load_profiles(algorithm: &str, profiles: &[LoadProfile], output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let folder = Path::new(output_folder);
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_besetzung.csv", algorithm)))?;
+    writer.write_record(["FAHRT", "FAHRZEUG", "SCHEDULE_ID", "LFD_NR", "ORT_NR", "ORT_NAME", "EINSTEIGER", "AUSSTEIGER", "BESETZUNG"])?;
+    for profile in profiles {
+        for point in &profile.points {
+            writer.write_record([
+                profile.trip_key.clone(),
+                profile.vehicle.clone(),
+                point.schedule_id.clone(),
+                point.lfnr.to_string(),
+                point.ort_nr.to_string(),
+                point.ort_name.clone(),
+                point.einsteiger.to_string(),
+                point.aussteiger.to_string(),
+                point.load.to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_besetzung_fahrten.csv", algorithm)))?;
+    writer.write_record([
+        "FAHRT", "FAHRZEUG", "LINIE", "RICHTUNG", "HALTESTELLEN", "MAX_BESETZUNG", "MAX_NACH_ORT_NR", "MAX_NACH_ORT_NAME",
+        "MIN_BESETZUNG", "BESETZUNG_ENDE", "NEGATIV", "NICHT_AUSGEGLICHEN",
+    ])?;
+    for profile in profiles {
+        let max_point = profile.max_load_point();
+        writer.write_record([
+            profile.trip_key.clone(),
+            profile.vehicle.clone(),
+            profile.line.to_string(),
+            profile.richtung.to_string(),
+            profile.points.len().to_string(),
+            profile.max_load.to_string(),
+            max_point.map(|point| point.ort_nr.to_string()).unwrap_or_default(),
+            max_point.map(|point| point.ort_name.clone()).unwrap_or_default(),
+            profile.min_load.to_string(),
+            profile.final_load.to_string(),
+            (profile.has_negative_load() as u8).to_string(),
+            (profile.is_unbalanced() as u8).to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
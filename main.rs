@@ -1,16 +1,18 @@
 // Importe der nötigen Bibliotheken
+use std::collections::HashMap;
 use std::error::Error;
-use std::fs;
-use std::fs::File;
-use std::mem::align_of;
-use std::{thread, time};
-use std::collections::{HashMap, HashSet};
-use std::time::{Instant, Duration};
-use std::io::{self, BufRead, BufReader, Write};
-use std::path::Path;
-use std::path::PathBuf;
+use std::time::Instant;
+use clap::{Parser, Subcommand};
 
 // Einbinden der Funktionen
+// Datenmodell
+mod model;                              // Importiert das gemeinsame Datenmodell
+mod timestamp;                          // Importiert die Zeitstempel der Fahrzeugereignisse
+mod schema;                             // Importiert die Spaltenzuordnung über die Kopfzeile
+mod rejects;                            // Importiert die Fehlerbehandlung auf Zeilenebene
+mod config;                             // Importiert die Konfiguration (Pfade, Toleranzen)
+mod matching_params;                    // Importiert die Toleranzprofile je Linie, Richtung und Tageszeit
+
 // Daten filtern
 
 // Liniendaten
@@ -24,178 +26,551 @@ mod remove_last_duplicates_in_busdata;  // Importiert die remove_last_duplicates
 
 // Algorithmus
 
+// Gemeinsame Schnittstelle der Algorithmen
+mod block_consistency;                  // Importiert die Zuordnung und Prüfung der Umläufe
+mod trip_score;                         // Importiert die Bewertung eines Fahrzeugs über eine ganze Fahrt
+mod event_index;                        // Importiert den räumlich-zeitlichen Index der Fahrzeugereignisse
+mod delay_model;                        // Importiert das Zeitfenster mit Verspätungsfortschreibung
+mod heuristic_cost;                     // Importiert die Kostenfunktion der Heuristik
+mod matcher;                            // Importiert die Schnittstelle TripMatcher und das gemeinsame Ergebnis
+mod global_assignment;                  // Importiert die konfliktfreie Gesamtzuordnung
+
 // Heuristischer Algorithmus
 mod algorithm_heuristic;                // Importiert den ersten Algorithmus
 
 // VF2 Algorithmus
-mod algorithm_vf2_part_1;               // Importiert den ersten Teil des VF2-Algorithmus
-mod algorithm_vf2_part_2;               // Importiert den zweiten Teil des VF2-Algorihtmus
+mod algorithm_vf2_part_1;               // Importiert den VF2-Algorithmus
 
 // Ergebnistest
 mod ergebnis_test;                      // Importiert den Test für die Ergebnisse
-
-// Main Methode
-fn main() -> Result<(), Box<dyn Error>> {
-
-    // Pfad zur Eingabe-CSV-Datei
-    println!("Starten des Programms mit einlesen der Dateien.");
-
-    // Pfade zu Dateien/Ordnern erstellen:
-    // Wichtig diese anzupassen!
-    let input_file_path = "/Users/martin/Downloads/diagnose/rohdaten_20231025.csv";                         // Pfad zur CSV-Busdatei
-    let csv_file_path = "/Users/martin/Downloads/diagnose/schedule_20231025.csv";                           // Pfad zur CSV-Fahrplandatei
-    let output_data = "/Users/martin/Desktop/Bachelorarbeit/test/GA/output.csv";                 // Pfad zur Ausgabedatei für heuristischen Algorithmus
-    let comparison_data = "/Users/martin/Downloads/diagnose/zahldaten_20231025.csv";                        // Pfad zur Vergleichsdatei
-    let output_folder_fahrplandaten = "/Users/martin/Desktop/Bachelorarbeit/test/GA/fahrplan";   // Pfad zum Fahrplanordner
-    let output_folder_busdaten = "/Users/martin/Desktop/Bachelorarbeit/test/GA/busdaten";        // Pfad zum Busdatenordner
-    let line_folder_path = "/Users/martin/Desktop/Bachelorarbeit/test/GA/output";                // Pfad zum Ordner der gefilterten Linien
-    let output_file = "/Users/martin/Desktop/Bachelorarbeit/test/GA/output_vf2.csv";             // Pfad zur Outputdatei des VF2 Algorithmus
-    let interim_save = "/Users/martin/Desktop/Bachelorarbeit/test/GA/zwischenspeicher.csv";            // Pfad zur Zwischenspeicher datei
-
-
-
-    //Starten der Datenfilterung
-    println!("Starten der Datenfilterung.");
-
-
-
-    // Schritt 1: Beginne mit dem Filtern der CSV-Fahrplan-Daten
-
+mod evaluation_breakdown;               // Importiert die Aufschlüsselung der Auswertung je Linie, Richtung, Stunde und Fahrzeug
+mod passenger_accuracy;                 // Importiert die Auswertung der Fahrgastzahlen
+
+// Analysen der zugeordneten Fahrten
+mod load_profile;                       // Importiert den Besetzungsverlauf je Fahrt
+mod passenger_balancing;                // Importiert den Ausgleich der Ein- und Aussteiger je Fahrt
+mod time_bands;                         // Importiert die Zeitbänder der Analysen
+mod od_matrix;                          // Importiert die Schätzung der Fahrgastströme (OD-Matrix)
+mod ridership;                          // Importiert das Fahrgastaufkommen je Haltestelle, Linie und Zeitband
+mod punctuality;                        // Importiert die Pünktlichkeit und den Verlauf der Verspätung
+mod dwell_time;                         // Importiert die Haltezeiten je Haltestelle und Zeitband
+mod running_time;                       // Importiert die Fahrzeiten zwischen aufeinanderfolgenden Haltestellen
+
+// Gesamtlauf im Speicher
+mod pipeline;                           // Importiert die In-Memory-Pipeline
+
+use config::{require, Config, Tolerances};
+use block_consistency::BlockSwitch;
+use ergebnis_test::EvaluationReport;
+use global_assignment::UnassignedTrip;
+use passenger_accuracy::ReferenceCount;
+use heuristic_cost::CostFunction;
+use matching_params::MatchingParams;
+use passenger_balancing::BalancingMethod;
+use matcher::{Algorithm, MatchResult, MatchedTrips, TripMatcher};
+use model::{ScheduleStop, VehicleEvent};
+use schema::InputOptions;
+use time_bands::TimeBands;
+
+// Kommandozeile: jeder Schritt der Pipeline einzeln oder alle zusammen
+#[derive(Parser)]
+#[command(about = "Zuordnung von Fahrzeugdaten zu Fahrplanfahrten")]
+struct Cli {
+    /// TOML-Konfigurationsdatei mit Pfaden, Einleseoptionen und Toleranzen
+    #[arg(long, global = true)]
+    config: Option<String>,
+
+    #[command(flatten)]
+    paths: config::Paths,
+
+    /// Fehlerhafte Zeilen verwerfen und in die Rejects-Datei schreiben statt abzubrechen
+    #[arg(long, global = true)]
+    lenient: bool,
+
+    /// Fahrten eines Umlaufs möglichst demselben Fahrzeug zuordnen
+    #[arg(long, global = true)]
+    block_consistency: bool,
+
+    /// Keinem Fahrzeug zwei sich zeitlich überschneidende Fahrten zuordnen
+    #[arg(long, global = true)]
+    conflict_free: bool,
+
+    /// CSV-Datei mit alternativen Spaltennamen (ALIAS;SPALTE)
+    #[arg(long, global = true)]
+    aliases: Option<String>,
+
+    /// Maximale Verspätung gegenüber der erwarteten Zeit in Sekunden
+    #[arg(long, global = true)]
+    time_tolerance: Option<u64>,
+
+    /// Maximale Verfrühung gegenüber der erwarteten Zeit in Sekunden
+    #[arg(long, global = true)]
+    early_tolerance: Option<u64>,
+
+    /// Verspätung nicht von Haltestelle zu Haltestelle fortschreiben (festes Fenster um die Soll-Zeit)
+    #[arg(long, global = true)]
+    no_delay_propagation: bool,
+
+    /// Kostenfunktion der Zuordnung (legacy = Produkt aus Entfernung und Zeitabweichung)
+    #[arg(long, global = true, value_enum)]
+    cost_function: Option<CostFunction>,
+
+    /// Verfahren für den Ausgleich der Ein- und Aussteiger je Fahrt (Analyse)
+    #[arg(long, global = true, value_enum)]
+    balancing: Option<BalancingMethod>,
+
+    /// Suchradius um eine Haltestelle für Kandidatenereignisse in Metern (Heuristik)
+    #[arg(long, global = true)]
+    candidate_radius: Option<f64>,
+
+    /// Maximale Abweichung der Fahrzeit zwischen zwei Haltestellen in Sekunden (VF2)
+    #[arg(long, global = true)]
+    max_delay: Option<u64>,
+
+    /// Maximale Entfernung zwischen Haltestelle und Fahrzeugereignis in Metern (VF2)
+    #[arg(long, global = true)]
+    match_distance: Option<f64>,
+
+    /// Maximale Anzahl ausgelassener Haltestellen je Fahrt (VF2)
+    #[arg(long, global = true)]
+    max_skipped_stops: Option<usize>,
+
+    /// Maximale Entfernung zur Referenzposition in Metern (Auswertung)
+    #[arg(long, global = true)]
+    evaluation_distance: Option<f64>,
+
+    /// Anzahl der Threads für Zuordnung und Auswertung (0 = alle Kerne)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Schritt 1: Fahrplan nach Linien aufteilen
+    SplitSchedule,
+    /// Schritt 2: Rohdaten nach Fahrzeugen aufteilen
+    SplitVehicles,
+    /// Schritte 3 und 4: Duplikate aus den Busdaten entfernen
+    Dedup,
+    /// Schritt 5: Fahrplan in einzelne Fahrten (Linienabschnitte) teilen
+    SplitTrips,
+    /// Schritt 6/7: Fahrten den Fahrzeugen zuordnen
+    Match {
+        #[arg(long, value_enum)]
+        algorithm: Algorithm,
+    },
+    /// Schritt 8: Ergebnis mit den Zähldaten vergleichen
+    Evaluate {
+        #[arg(long, value_enum, default_value = "heuristic")]
+        algorithm: Algorithm,
+    },
+    /// Schritt 9: Zugeordnete Fahrten analysieren (Besetzung, Ausgleich der Fahrgastzahlen, Fahrgastströme, Fahrgastaufkommen)
+    Analyze {
+        #[arg(long, value_enum, default_value = "heuristic")]
+        algorithm: Algorithm,
+    },
+    /// Alle Schritte nacheinander im Speicher ausführen
+    All {
+        /// Zwischenordner (fahrplan, busdaten, fahrten) zur Fehlersuche schreiben
+        #[arg(long)]
+        write_intermediate: bool,
+    },
+}
+
+// Führt einen Schritt aus und gibt die Dauer aus
+fn timed<T>(step: impl FnOnce() -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
     // Zeitmessung starten
     let start_time = Instant::now();
-    println!("\nSchritt 1: Beginne mit dem Filtern der CSV-Fahrplan-Daten.");
-    filter_lines_in_csv_schedule::filter_lines_in_csv_schedule(csv_file_path, output_folder_fahrplandaten);
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
+    let result = step()?;
     // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
+    let duration = start_time.elapsed();
     // Gib die Dauer aus
     println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
-    println!("Schritt 1 abgeschlossen: Daten nach Linien gefiltert und in fahrplan gespeichert.");
-   
+    Ok(result)
+}
 
+// Schritt 1: Filtern der CSV-Fahrplan-Daten
+fn split_schedule(config: &Config, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    let csv_file_path = require(&config.paths.schedule, "schedule")?;
+    let output_folder_fahrplandaten = require(&config.paths.fahrplan_ordner, "fahrplan_ordner")?;
+
+    println!("\nSchritt 1: Beginne mit dem Filtern der CSV-Fahrplan-Daten.");
+    timed(|| filter_lines_in_csv_schedule::filter_lines_in_csv_schedule(csv_file_path, output_folder_fahrplandaten, input_options))?;
+    println!("Schritt 1 abgeschlossen: Daten nach Linien gefiltert und in fahrplan gespeichert.");
+    Ok(())
+}
 
-    // Schritt 2: Beginne mit dem Filtern der CSV-Bus-Daten
+// Schritt 2: Filtern der CSV-Bus-Daten
+fn split_vehicles(config: &Config, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    let input_file_path = require(&config.paths.rohdaten, "rohdaten")?;
+    let output_folder_busdaten = require(&config.paths.busdaten_ordner, "busdaten_ordner")?;
 
-    // Zeitmessung starten
-    let start_time = Instant::now();
     println!("\nSchritt 2: Beginne mit dem Filtern der CSV-Bus-Daten.");
-    filter_bus_in_csv_rohdaten::filter_bus_in_csv_rohdaten(output_folder_busdaten, input_file_path);
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
-    // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
-    // Gib die Dauer aus
-    println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
+    timed(|| filter_bus_in_csv_rohdaten::filter_bus_in_csv_rohdaten(output_folder_busdaten, input_file_path, input_options))?;
     println!("Schritt 2 abgeschlossen: Daten nach Busse gefiltert und in busdaten gespeichert.");
-    
-
+    Ok(())
+}
 
-    // Schritt 3: Funktion aufrufen, um die Duplikate aus den Busdaten zu entfernen
+// Schritte 3 und 4: Entfernen der Duplikate aus den Busdaten
+fn dedup(config: &Config, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    let output_folder_busdaten = require(&config.paths.busdaten_ordner, "busdaten_ordner")?;
 
-    // Zeitmessung starten
-    let start_time = Instant::now();
-    println!("Schritt 3: Funktion aufrufen, um die Duplikate aus den Busdaten zu entfernen");    
-    remove_duplicates_in_busdata::process_csv_files_in_folder(output_folder_busdaten);
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
-    // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
-    // Gib die Dauer aus
-    println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
+    println!("Schritt 3: Funktion aufrufen, um die Duplikate aus den Busdaten zu entfernen");
+    timed(|| remove_duplicates_in_busdata::process_csv_files_in_folder(output_folder_busdaten, input_options))?;
     println!("Schritt 3 abgeschlossen: Dateien erstellt.");
 
+    println!("Schritt 4: Funktion aufrufen, um die letzten Duplikate aus den Busdaten zu entfernen");
+    timed(|| remove_last_duplicates_in_busdata::remove_last_duplicates_in_busdata(output_folder_busdaten, input_options))?;
+    println!("Schritt 4 abgeschlossen: Dateien erstellt.");
+    Ok(())
+}
 
+// Schritt 5: Teilen der Linien in einzelne Abschnitte
+fn split_trips(config: &Config, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    let csv_file_path = require(&config.paths.schedule, "schedule")?;
+    let line_folder_path = require(&config.paths.fahrten_ordner, "fahrten_ordner")?;
 
-    // Schritt 4: Aufruf der Funktion zur Filterung der CSV-Dateien im Verzeichnis
+    println!("Schritt 5: Funktion aufrufen, um die Linien in einzelne Abschnitte zu teilen und speichern in eigenen csv dateien");
+    timed(|| line_section_split::run(csv_file_path, line_folder_path, input_options))?;
+    println!("Schritt 5 abgeschlossen: Linien in einzelne Abschnitte geteilt und in eigenen csv dateien gespeichert");
+    Ok(())
+}
+
+// Schritt 6 bzw. 7: Ausführen eines Algorithmus auf den Fahrten und den bereinigten Busdaten
+fn run_matching(config: &Config, params: &MatchingParams, input_options: &InputOptions, algorithm: Algorithm) -> Result<(), Box<dyn Error>> {
+    let line_folder_path = require(&config.paths.fahrten_ordner, "fahrten_ordner")?;
+    let output_folder_busdaten = require(&config.paths.busdaten_ordner, "busdaten_ordner")?;
+    let matcher = algorithm.matcher(params, &config.heuristic);
+
+    println!("Schritt 6/7: Aufruf des Algorithmus {}", matcher.name());
+    let trips = model::read_schedule_trips_from_folder(line_folder_path, input_options)?;
+    let vehicles = model::read_vehicle_events_from_folder(output_folder_busdaten, input_options)?;
+    println!("Fahrten und Busse eingelesen");
+    let (result, switches, unassigned) = timed(|| Ok(match_with_blocks(config, matcher.as_ref(), &trips, &vehicles)))?;
+    export_result(config, algorithm, &result)?;
+    write_block_switches(config, &[(matcher.name().to_string(), switches)])?;
+    write_unassigned(config, &[(matcher.name().to_string(), unassigned)])?;
+    println!("Schritt 6/7 abgeschlossen: Algorithmus {} durchgeführt.", matcher.name());
+    Ok(())
+}
+
+// Führt einen Algorithmus aus, ordnet auf Wunsch ganze Umläufe und konfliktfrei zu und sucht die Fahrzeugwechsel
+// innerhalb der Umläufe; liefert zusätzlich die Fahrten, die in der konfliktfreien Zuordnung ohne Fahrzeug bleiben
+fn match_with_blocks(config: &Config, matcher: &dyn TripMatcher, trips: &HashMap<String, Vec<ScheduleStop>>, vehicles: &HashMap<String, Vec<VehicleEvent>>) -> (MatchResult, Vec<BlockSwitch>, Vec<UnassignedTrip>) {
+    // Die konfliktfreie Zuordnung braucht alle Kandidaten, sonst genügt der beste je Fahrt
+    let candidates = config.matching.conflict_free.then(|| matcher.match_candidates(trips, vehicles));
+    let mut result = match &candidates {
+        Some(candidates) => MatchResult::from_candidates(candidates),
+        None => matcher.match_trips(trips, vehicles),
+    };
+    if config.matching.block_consistency {
+        result = block_consistency::enforce_blocks(matcher, trips, vehicles, result, config.matching.min_block_coverage);
+    }
+    let mut unassigned = Vec::new();
+    if let Some(candidates) = &candidates {
+        // Die Zuordnung der Umläufe bleibt erhalten, soweit sie keinen Konflikt verursacht
+        let preferred = config.matching.block_consistency.then_some(&result);
+        let (conflict_free, open, incomplete) = global_assignment::assign(trips, candidates, preferred);
+        println!("{} Fahrten ohne konfliktfreies Fahrzeug.", open.len());
+        if incomplete > 0 {
+            println!("{} Konfliktgruppen nach der Höchstzahl der Suchzustände abgebrochen, dort gilt die beste gefundene Zuordnung.", incomplete);
+        }
+        result = conflict_free;
+        unassigned = open;
+    }
+    let switches = block_consistency::find_block_switches(trips, &result);
+    println!("{} Fahrzeugwechsel innerhalb der Umläufe.", switches.len());
+    (result, switches, unassigned)
+}
+
+// Schreibt die Fahrten ohne Fahrzeug aller Algorithmen, sofern die konfliktfreie Zuordnung aktiv und ein Pfad angegeben ist
+fn write_unassigned(config: &Config, unassigned: &[(String, Vec<UnassignedTrip>)]) -> Result<(), Box<dyn Error>> {
+    if let (true, Some(output_file)) = (config.matching.conflict_free, &config.paths.nicht_zugeordnet) {
+        let rows: Vec<(String, UnassignedTrip)> = unassigned.iter()
+            .flat_map(|(algorithm, trips)| trips.iter().map(move |trip| (algorithm.clone(), trip.clone())))
+            .collect();
+        global_assignment::write_unassigned(&rows, output_file)?;
+    }
+    Ok(())
+}
+
+// Schreibt die Fahrzeugwechsel innerhalb der Umläufe aller Algorithmen, sofern ein Pfad angegeben ist
+fn write_block_switches(config: &Config, switches: &[(String, Vec<BlockSwitch>)]) -> Result<(), Box<dyn Error>> {
+    if let Some(output_file) = &config.paths.umlauf_wechsel {
+        let rows: Vec<(String, BlockSwitch)> = switches.iter()
+            .flat_map(|(algorithm, switches)| switches.iter().map(move |switch| (algorithm.clone(), switch.clone())))
+            .collect();
+        block_consistency::write_block_switches(&rows, output_file)?;
+    }
+    Ok(())
+}
 
-    // Zeitmessung starten
-    let start_time = Instant::now();
-    println!("Schritt 4: Funktion aufrufen, um die letzten Duplikate aus den Busdaten zu entfernen");    
-    remove_last_duplicates_in_busdata::remove_last_duplicates_in_busdata(output_folder_busdaten)?;
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
-    // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
-    // Gib die Dauer aus
-    println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
-    println!("Schritt 4 abgeschlossen: Dateien erstellt.");
+// Schreibt das Ergebnis eines Algorithmus und, sofern ein Pfad angegeben ist, die Zuordnung Fahrt -> Fahrzeug mit Bewertung
+fn export_result(config: &Config, algorithm: Algorithm, result: &MatchResult) -> Result<(), Box<dyn Error>> {
+    result.write_stops(algorithm.output_path(&config.paths)?)?;
+    if let Some(assignment_file) = algorithm.assignment_path(&config.paths) {
+        result.write_assignment(assignment_file)?;
+    }
+    println!("{} Fahrten zugeordnet, {} Haltestellen.", result.trips.len(), result.trips.values().map(|trip_match| trip_match.stops.len()).sum::<usize>());
+    Ok(())
+}
 
+// Schritt 8: Vergleich der Ergebnisse mit den Zähldaten
+fn evaluate(config: &Config, params: &MatchingParams, input_options: &InputOptions, algorithm: Algorithm) -> Result<(), Box<dyn Error>> {
+    let output_data = algorithm.output_path(&config.paths)?;
+    let comparison_data = require(&config.paths.zahldaten, "zahldaten")?;
+    let line_folder_path = require(&config.paths.fahrten_ordner, "fahrten_ordner")?;
+
+    // Vergleiche die Daten und gib Precision, Recall und F1 der Zuordnung aus
+    println!("Schritt 8: Testen der Ergebnisse gestartet.");
+    let trips = model::read_schedule_trips_from_folder(line_folder_path, input_options)?;
+    let matched = model::read_matched_stops(output_data, input_options)?;
+    let report = ergebnis_test::test_results(&matched, comparison_data, &trips, input_options, params)?;
+    report.print();
+    write_breakdown(config, algorithm, &report)?;
+    if let Some(counts) = passenger_accuracy::load_reference_counts(comparison_data, input_options)? {
+        evaluate_passengers(config, algorithm, &matched, &counts, &trips)?;
+    }
+    println!("Schritt 8 abgeschlossen: Testen der Ergebnisse abgeschlossen.");
+    Ok(())
+}
+
+// Schreibt die Aufschlüsselung der Auswertung eines Algorithmus, sofern ein Ordner angegeben ist
+fn write_breakdown(config: &Config, algorithm: Algorithm, report: &EvaluationReport) -> Result<(), Box<dyn Error>> {
+    if let Some(output_folder) = &config.paths.auswertung_ordner {
+        let breakdown = evaluation_breakdown::breakdown(report, config.evaluation.worst_trips);
+        evaluation_breakdown::write_breakdown(algorithm.name(), report, &breakdown, output_folder)?;
+        println!("Aufschlüsselung der Auswertung nach {} geschrieben.", output_folder);
+    }
+    Ok(())
+}
+
+// Vergleicht die Fahrgastzahlen eines Algorithmus mit den Zähldaten und schreibt sie je Fahrt, sofern ein Ordner angegeben ist
+fn evaluate_passengers(config: &Config, algorithm: Algorithm, matched: &[(ScheduleStop, VehicleEvent)], counts: &[ReferenceCount], trips: &HashMap<String, Vec<ScheduleStop>>) -> Result<(), Box<dyn Error>> {
+    let report = passenger_accuracy::evaluate_counts(matched, counts, trips, config.evaluation.count_tolerance);
+    report.print();
+    if let Some(output_folder) = &config.paths.auswertung_ordner {
+        passenger_accuracy::write_trip_counts(algorithm.name(), &report, output_folder)?;
+    }
+    Ok(())
+}
 
+// Schritt 9: Analysen der zugeordneten Fahrten aus der Ergebnisdatei eines Algorithmus
+fn analyze(config: &Config, params: &MatchingParams, input_options: &InputOptions, algorithm: Algorithm) -> Result<(), Box<dyn Error>> {
+    let output_data = algorithm.output_path(&config.paths)?;
+    let output_folder = require(&config.paths.analyse_ordner, "analyse_ordner")?;
 
-    // Schritt 5: Teilen der Linien in einzelne abschnitte und speichern in eigenen csv dateien
+    println!("Schritt 9: Analyse der zugeordneten Fahrten gestartet.");
+    let trips = matcher::group_matched_stops(model::read_matched_stops(output_data, input_options)?);
+    analyze_trips(config, params, algorithm, &trips, output_folder)?;
+    println!("Schritt 9 abgeschlossen: Analysen in {} gespeichert.", output_folder);
+    Ok(())
+}
+
+// Berechnet die Analysen der zugeordneten Fahrten eines Algorithmus und schreibt sie in den Ordner `output_folder`
+fn analyze_trips(config: &Config, params: &MatchingParams, algorithm: Algorithm, trips: &MatchedTrips, output_folder: &str) -> Result<(), Box<dyn Error>> {
+    let profiles = load_profile::load_profiles(trips);
+    let negative = profiles.iter().filter(|profile| profile.has_negative_load()).count();
+    let unbalanced = profiles.iter().filter(|profile| profile.is_unbalanced()).count();
+    println!("Besetzung: {} Fahrten, {} mit negativer Besetzung, {} nicht ausgeglichen.", profiles.len(), negative, unbalanced);
+    load_profile::write_load_profiles(algorithm.name(), &profiles, output_folder)?;
+
+    let balanced = passenger_balancing::balance_trips(trips, config.balancing.method, params);
+    let corrected = balanced.iter().filter(|trip| trip.was_corrected()).count();
+    println!("Ausgleich ({:?}): {} von {} Fahrten korrigiert.", config.balancing.method, corrected, balanced.len());
+    passenger_balancing::write_balanced_trips(algorithm.name(), &balanced, output_folder)?;
+
+    let time_bands = TimeBands::new(&config.time_bands, config.input.time_zone())?;
+    let without_band = trips.values().flatten().filter(|(stop, _)| time_bands.stop_minute(stop).is_none()).count();
+    if without_band > 0 {
+        println!("Zeitbänder: {} Haltestellen ohne Soll-Zeit (ZEIT) unter 'unbekannt'.", without_band);
+    }
+    let matrices = od_matrix::estimate_trips(trips, &balanced, &time_bands, &config.od);
+    let not_converged = matrices.iter().filter(|matrix| !matrix.converged).count();
+    println!("Fahrgastströme: {} Fahrten geschätzt, {} ohne Konvergenz nach {} Durchläufen.", matrices.len(), not_converged, config.od.max_iterations);
+    od_matrix::write_od(algorithm.name(), &matrices, &config.od, output_folder)?;
+
+    let tables = ridership::aggregate(trips, &balanced, &time_bands);
+    println!("Fahrgastaufkommen: {} Gruppen je Haltestelle, {} je Linie und Richtung.", tables.by_stop.len(), tables.by_line.len());
+    ridership::write_ridership(algorithm.name(), &tables, output_folder)?;
+
+    let (delays, without_schedule) = punctuality::stop_delays(trips, &time_bands, &config.punctuality);
+    let trip_delays = punctuality::trip_delays(&delays);
+    let rows = punctuality::punctuality_rows(&delays);
+    let on_time = delays.iter().filter(|delay| delay.punctuality == punctuality::Punctuality::OnTime).count();
+    println!("Pünktlichkeit: {} von {} Halten pünktlich ({} s zu früh bis {} s zu spät, {} ohne Soll-Zeit übersprungen).", on_time, delays.len(), config.punctuality.early_seconds, config.punctuality.late_seconds, without_schedule);
+    punctuality::write_punctuality(algorithm.name(), &delays, &trip_delays, &rows, output_folder)?;
+
+    let (dwell_events, skipped) = dwell_time::dwell_events(trips, &time_bands, &config.dwell);
+    let dwell_stats = dwell_time::aggregate(&dwell_events);
+    match dwell_time::fit(&dwell_events) {
+        Some(fit) => println!("Haltezeiten: {} Halte ({} ohne auswertbare Haltezeit), {:.1} s Grundzeit und {:.2} s je Fahrgast.", dwell_events.len(), skipped, fit.intercept_s, fit.seconds_per_passenger),
+        None => println!("Haltezeiten: {} Halte ({} ohne auswertbare Haltezeit).", dwell_events.len(), skipped),
+    }
+    dwell_time::write_dwell_times(algorithm.name(), &dwell_events, &dwell_stats, output_folder)?;
 
-    // Zeitmessung starten
-    let start_time = Instant::now();
-    println!("Schritt 5: Funktion aufrufen, um die Linien in einzelne Abschnitte zu teilen und speichern in eigenen csv dateien");    
-    line_section_split::run(csv_file_path, line_folder_path)?;   
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
-    // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
-    // Gib die Dauer aus
-    println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
-    println!("Schritt 5 abgeschlossen: Funktion aufrufen, um die Linien in einzelne Abschnitte zu teilen und speichern in eigenen csv dateien"); 
-    
+    let (runs, skipped) = running_time::segment_runs(trips, &time_bands);
+    let segment_stats = running_time::aggregate(&runs, &config.running_times)?;
+    let slower = runs.iter().filter(|run| run.difference_s() > 0).count();
+    println!("Fahrzeiten: {} Abschnitte ({} mit nicht zugeordneter Haltestelle dazwischen, {} ohne gültige Soll-Fahrzeit, {} mit negativer Fahrzeit übersprungen), {} länger als geplant, {} Gruppen je Abschnitt und Zeitband.", runs.len(), skipped.not_adjacent, skipped.no_schedule, skipped.negative, slower, segment_stats.len());
+    running_time::write_running_times(algorithm.name(), &runs, &segment_stats, &config.running_times, output_folder)?;
+    Ok(())
+}
 
+// Alle Schritte im Speicher: Fahrplan und Rohdaten werden je einmal gelesen, nur die Ergebnisse werden geschrieben
+fn run_all(config: &Config, params: &MatchingParams, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    let csv_file_path = require(&config.paths.schedule, "schedule")?;
+    let input_file_path = require(&config.paths.rohdaten, "rohdaten")?;
 
+    //Starten der Datenfilterung
+    println!("Schritte 1 bis 5: Einlesen und Aufbereiten der Fahrplan- und Busdaten im Speicher.");
+    let data = timed(|| pipeline::prepare(csv_file_path, input_file_path, input_options))?;
+    println!("Schritte 1 bis 5 abgeschlossen: {} Linien, {} Fahrzeuge, {} Fahrten.", data.stops_by_line.len(), data.vehicles.len(), data.trips.len());
+    if config.pipeline.write_intermediate {
+        pipeline::write_intermediate(&data, &config.paths)?;
+        println!("Zwischenergebnisse geschrieben.");
+    }
 
-    // Starten der Algorithmen
+    // Vergleichsdaten für die Tests, Fahrgastzahlen nur sofern die Spalten vorhanden sind
+    let comparison = match &config.paths.zahldaten {
+        Some(comparison_data) => Some(ergebnis_test::load_data_comparison(comparison_data, input_options)?),
+        None => None,
+    };
+    let counts = match &config.paths.zahldaten {
+        Some(comparison_data) => passenger_accuracy::load_reference_counts(comparison_data, input_options)?,
+        None => None,
+    };
+
+    // Starten der Algorithmen und Testen der Ergebnisse
     println!("\n Es folgen die Algorithmen.");
+    let mut switches = Vec::new();
+    let mut unassigned = Vec::new();
+    for algorithm in Algorithm::ALL {
+        let matcher = algorithm.matcher(params, &config.heuristic);
+        println!("Aufruf des Algorithmus {}", matcher.name());
+        let (result, block_switches, open) = timed(|| Ok(match_with_blocks(config, matcher.as_ref(), &data.trips, &data.vehicles)))?;
+        export_result(config, algorithm, &result)?;
+        switches.push((matcher.name().to_string(), block_switches));
+        unassigned.push((matcher.name().to_string(), open));
+
+        if let Some(comparison) = &comparison {
+            println!("Testen der Ergebnisse des Algorithmus {}", matcher.name());
+            let report = ergebnis_test::evaluate_result(&result, comparison, &data.trips, params);
+            report.print();
+            write_breakdown(config, algorithm, &report)?;
+        }
+        if let Some(counts) = &counts {
+            evaluate_passengers(config, algorithm, &result.stops(), counts, &data.trips)?;
+        }
+        if let Some(output_folder) = &config.paths.analyse_ordner {
+            analyze_trips(config, params, algorithm, &matcher::group_matched_stops(result.stops()), output_folder)?;
+        }
+    }
+    write_block_switches(config, &switches)?;
+    write_unassigned(config, &unassigned)?;
+    Ok(())
+}
+
+// Führt die Konfigurationsdatei und die Angaben der Kommandozeile zusammen (Kommandozeile hat Vorrang)
+fn build_config(cli: &Cli) -> Result<Config, Box<dyn Error>> {
+    let mut config = match &cli.config {
+        Some(file) => config::load_config(file)?,
+        None => Config::default(),
+    };
+
+    config.paths.merge(&cli.paths);
+    if cli.lenient {
+        config.input.lenient = true;
+    }
+    if cli.block_consistency {
+        config.matching.block_consistency = true;
+    }
+    if cli.conflict_free {
+        config.matching.conflict_free = true;
+    }
+    if let Some(aliases) = &cli.aliases {
+        config.input.aliases_file = Some(aliases.clone());
+    }
 
-    // Schritt 6: Aufruf der Funktion zum starten des erweiterten ersten Heuristischen-Algorithmus
-    println!("Schritt 6: Aufruf der Funktion zum starten des Heuristischen-Algorithmus");  
-    // Zeitmessung starten
-    let start_time = Instant::now();  
-    let lines = algorithm_heuristic::read_lines(csv_file_path)?;
-    println!("Schritt 6.1: Linien eingelesen");
-    let bus= algorithm_heuristic::read_bus_files(output_folder_busdaten)?;
-    println!("Schritt 6.2: Busse eingelesen");
-    algorithm_heuristic::run_algorithm(lines, bus, output_data);
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
-    // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
-    // Gib die Dauer aus
-    println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
-    println!("Schritt 6 abgeschlossen: Heuristischer-Algorithmus durchgeführt.");
- 
+    if let Some(cost_function) = cli.cost_function {
+        config.heuristic.cost_function = cost_function;
+    }
+    if let Some(method) = cli.balancing {
+        config.balancing.method = method;
+    }
 
+    if let Some(threads) = cli.threads {
+        config.pipeline.threads = threads;
+    }
 
-    // Schritt 7: Aufruf der Funktion zum starten des erweiterten ersten Heuristischen-Algorithmus
-    println!("Schritt 7.1: Aufruf der ersten Funktion zum starten des VF2-Algorithmus");  
-    // Zeitmessung starten
-    let start_time = Instant::now();  
-    if let Err(err) = algorithm_vf2_part_1::run(line_folder_path, output_folder_busdaten, &interim_save) {
-        eprintln!("Fehler beim Ausführen des Programms: {}", err);
+    let tolerances: &mut Tolerances = &mut config.tolerances;
+    if let Some(value) = cli.time_tolerance {
+        tolerances.time_tolerance_seconds = value;
     }
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
-    // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
-    // Gib die Dauer aus
-    println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
-    println!("Schritt 7.1 abgeschlossen: Aufruf der ersten Funktion zum starten des VF2-Algorithmus");  
-    
-    println!("Schritt 7.2: Aufruf der zweiten Funktion zum starten des VF2-Algorithmus");  
-    // Zeitmessung starten
-    let start_time = Instant::now();  
-    algorithm_vf2_part_2::run(line_folder_path, output_folder_busdaten, &interim_save);
-    // Beende die Zeitmessung
-    let end_time = Instant::now();
-    // Berechne die Dauer der Funktion
-    let duration = end_time - start_time;
-    // Gib die Dauer aus
-    println!("Die Funktion hat {} Sekunden gedauert.", duration.as_secs_f64());
-    println!("Schritt 7.2 abgeschlossen: Aufruf der zweiten Funktion zum starten des VF2-Algorithmus");  
-    
+    if let Some(value) = cli.early_tolerance {
+        tolerances.early_tolerance_seconds = value;
+    }
+    if cli.no_delay_propagation {
+        tolerances.propagate_delay = false;
+    }
+    if let Some(value) = cli.candidate_radius {
+        tolerances.candidate_radius_m = value;
+    }
+    if let Some(value) = cli.max_delay {
+        tolerances.max_delay_seconds = value;
+    }
+    if let Some(value) = cli.match_distance {
+        tolerances.match_distance_m = value;
+    }
+    if let Some(value) = cli.max_skipped_stops {
+        tolerances.max_skipped_stops = value;
+    }
+    if let Some(value) = cli.evaluation_distance {
+        tolerances.evaluation_distance_m = value;
+    }
+    Ok(config)
+}
 
+// Main Methode
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let config = build_config(&cli)?;
+    let input_options = config.input.to_input_options()?;
+    let params = MatchingParams::new(config.tolerances, &config.profiles, config.input.time_zone())?;
+    if config.pipeline.threads > 0 {
+        rayon::ThreadPoolBuilder::new().num_threads(config.pipeline.threads).build_global()?;
+    }
 
-    // Starten der Tests
-    // Vergleiche die Daten und gib die Abweichung in Prozent aus
-    println!("Schritt 8: Testen der Ergebnisse gestartet.");
-    let percentage_difference = ergebnis_test::test_results(&output_data, &comparison_data);
-    println!("Schritt 8 abgeschlossen: Testen der Ergebnisse abgeschlossen.");
+    println!("Starten des Programms mit einlesen der Dateien.");
+
+    match cli.command {
+        Command::SplitSchedule => split_schedule(&config, &input_options)?,
+        Command::SplitVehicles => split_vehicles(&config, &input_options)?,
+        Command::Dedup => dedup(&config, &input_options)?,
+        Command::SplitTrips => split_trips(&config, &input_options)?,
+        Command::Match { algorithm } => run_matching(&config, &params, &input_options, algorithm)?,
+        Command::Evaluate { algorithm } => evaluate(&config, &params, &input_options, algorithm)?,
+        Command::Analyze { algorithm } => analyze(&config, &params, &input_options, algorithm)?,
+        Command::All { write_intermediate } => {
+            let mut config = config.clone();
+            config.pipeline.write_intermediate |= write_intermediate;
+            run_all(&config, &params, &input_options)?;
+        }
+    }
+
+    // Verworfene Zeilen ausgeben und speichern
+    if config.input.lenient {
+        input_options.rejects.print_summary();
+        if let Some(rejects_file) = &config.paths.rejects {
+            if !input_options.rejects.is_empty() {
+                input_options.rejects.write_csv(rejects_file)?;
+                println!("Verworfene Zeilen gespeichert in: {}", rejects_file);
+            }
+        }
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,209 @@
+// Gemeinsame Schnittstelle der Zuordnungsalgorithmen: jeder Algorithmus erhält die Fahrten des Fahrplans und die
+// Ereignisse der Fahrzeuge und liefert ein MatchResult, das Kommandozeile, Auswertung und Export gleich behandeln
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use clap::ValueEnum;
+use rayon::prelude::*;
+
+use crate::algorithm_heuristic;
+use crate::algorithm_vf2_part_1;
+use crate::config::{require, Paths};
+use crate::event_index::EventTimeline;
+use crate::heuristic_cost::HeuristicCost;
+use crate::line_section_split;
+use crate::matching_params::MatchingParams;
+use crate::model::{self, ScheduleStop, VehicleEvent};
+use crate::trip_score::TripMatch;
+
+// Ergebnis eines Algorithmus: je Fahrt das zugeordnete Fahrzeug, die Bewertung und die einzelnen Haltestellen
+#[derive(Debug, Clone, Default)]
+pub struct MatchResult {
+    pub trips: BTreeMap<String, TripMatch>, // Fahrt -> Zuordnung, nach Fahrt sortiert
+}
+
+impl MatchResult {
+    pub fn from_trip_matches(matches: Vec<TripMatch>) -> Self {
+        let mut result = MatchResult::default();
+        for trip_match in matches {
+            result.insert(trip_match);
+        }
+        result
+    }
+
+    // Je Fahrt der beste Kandidat
+    pub fn from_candidates(candidates: &BTreeMap<String, Vec<TripMatch>>) -> Self {
+        MatchResult::from_trip_matches(candidates.values().filter_map(|matches| matches.first().cloned()).collect())
+    }
+
+    // Fügt die Zuordnung einer Fahrt hinzu bzw. ersetzt sie
+    pub fn insert(&mut self, trip_match: TripMatch) {
+        self.trips.insert(trip_match.trip_key.clone(), trip_match);
+    }
+
+    // Fahrzeug einer Fahrt
+    pub fn vehicle(&self, trip_key: &str) -> Option<&str> {
+        self.trips.get(trip_key).map(|trip_match| trip_match.vehicle.as_str())
+    }
+
+    // Alle zugeordneten Haltestellen in der Reihenfolge der Fahrten
+    pub fn stops(&self) -> Vec<(ScheduleStop, VehicleEvent)> {
+        self.trips.values().flat_map(|trip_match| trip_match.stops.iter().cloned()).collect()
+    }
+
+    // Schreibt die zugeordneten Haltestellen (gleiches Format für alle Algorithmen)
+    pub fn write_stops(&self, output_file: &str) -> Result<(), Box<dyn Error>> {
+        model::write_matched_stops(output_file, &self.stops())
+    }
+
+    // Schreibt die Zuordnung Fahrt -> Fahrzeug mit der Bewertung
+    pub fn write_assignment(&self, output_file: &str) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::WriterBuilder::new().delimiter(b';').from_path(output_file)?;
+        writer.write_record(["Line_ID", "Bus_ID", "Stops", "Covered", "Spatial_Error_m", "Temporal_Error_s", "Cost"])?;
+        for (trip_key, trip_match) in &self.trips {
+            let score = &trip_match.score;
+            writer.write_record([
+                trip_key.clone(),
+                trip_match.vehicle.clone(),
+                score.stops_total.to_string(),
+                score.stops_covered.to_string(),
+                format!("{:.1}", score.spatial_error_m),
+                score.temporal_error_s.to_string(),
+                format!("{:.3}", score.cost),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+// Zugeordnete Haltestellen je Fahrt, nach LFD_NR und Soll-Zeit sortiert (Grundlage der Analysen nach der Zuordnung)
+pub type MatchedTrips = BTreeMap<String, Vec<(ScheduleStop, VehicleEvent)>>;
+
+// Gruppiert zugeordnete Haltestellen, z.B. aus einer Ergebnisdatei, nach Fahrten
+pub fn group_matched_stops(matched: Vec<(ScheduleStop, VehicleEvent)>) -> MatchedTrips {
+    let mut trips = MatchedTrips::new();
+    for (stop, event) in matched {
+        trips.entry(line_section_split::trip_key(&stop)).or_default().push((stop, event));
+    }
+    for stops in trips.values_mut() {
+        stops.sort_by_key(|(stop, _)| (stop.lfnr, stop.zeit));
+    }
+    trips
+}
+
+// Schnittstelle, die jeder Algorithmus umsetzt
+pub trait TripMatcher: Sync {
+    // Name des Algorithmus für die Ausgaben
+    fn name(&self) -> &str;
+
+    // Bewertet für jede Fahrt (Schlüssel -> Haltestellen) alle in Frage kommenden Fahrzeuge (Fahrzeug -> Ereignisse),
+    // je Fahrt von der besten zur schlechtesten Bewertung sortiert
+    fn match_candidates(&self, trips: &HashMap<String, Vec<ScheduleStop>>, vehicles: &HashMap<String, Vec<VehicleEvent>>) -> BTreeMap<String, Vec<TripMatch>>;
+
+    // Ordnet jeder Fahrt das Fahrzeug mit der besten Bewertung zu
+    fn match_trips(&self, trips: &HashMap<String, Vec<ScheduleStop>>, vehicles: &HashMap<String, Vec<VehicleEvent>>) -> MatchResult {
+        MatchResult::from_candidates(&self.match_candidates(trips, vehicles))
+    }
+
+    // Ordnet eine Fahrt einem vorgegebenen Fahrzeug zu (z.B. für die Zuordnung ganzer Umläufe)
+    fn match_trip_to_vehicle(&self, trip_key: &str, stops: &[ScheduleStop], vehicle: &str, events: &[VehicleEvent]) -> Option<TripMatch>;
+}
+
+// Heuristischer Algorithmus: je Fahrt wird jedes Fahrzeug mit Kandidatenereignissen über alle Haltestellen bewertet
+// (je Haltestelle das Ereignis mit den geringsten Kosten nach der Kostenfunktion) und das beste gewählt
+pub struct HeuristicMatcher {
+    pub params: MatchingParams,
+    pub cost: HeuristicCost,
+}
+
+impl TripMatcher for HeuristicMatcher {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn match_candidates(&self, trips: &HashMap<String, Vec<ScheduleStop>>, vehicles: &HashMap<String, Vec<VehicleEvent>>) -> BTreeMap<String, Vec<TripMatch>> {
+        let mut vehicle_ids: Vec<&String> = vehicles.keys().collect();
+        vehicle_ids.sort();
+        let index = algorithm_heuristic::build_index(vehicle_ids.into_iter().flat_map(|vehicle| vehicles[vehicle].iter()), &self.params.default);
+        let timelines: HashMap<&str, EventTimeline> = vehicles.iter()
+            .map(|(vehicle, events)| (vehicle.as_str(), EventTimeline::new(events)))
+            .collect();
+
+        let mut trip_keys: Vec<&String> = trips.keys().collect();
+        trip_keys.sort();
+
+        // Die Fahrten werden parallel bearbeitet; collect behält die Reihenfolge der Schlüssel bei
+        trip_keys.into_par_iter()
+            .map(|trip_key| {
+                let tolerances = self.params.for_trip(&trips[trip_key]);
+                (trip_key.clone(), algorithm_heuristic::match_trip_candidates(trip_key, &trips[trip_key], &index, &timelines, &self.cost, &tolerances))
+            })
+            .collect()
+    }
+
+    fn match_trip_to_vehicle(&self, trip_key: &str, stops: &[ScheduleStop], vehicle: &str, events: &[VehicleEvent]) -> Option<TripMatch> {
+        algorithm_heuristic::match_trip_to_vehicle(trip_key, stops, vehicle, events, &self.cost, &self.params.for_trip(stops))
+    }
+}
+
+// VF2-Algorithmus: Suche der Fahrt als Teilgraph im Ereignisgraphen jedes Fahrzeugs
+pub struct Vf2Matcher {
+    pub params: MatchingParams,
+    pub cost: HeuristicCost, // Bewertung der Fahrzeuge einer Fahrt
+}
+
+impl TripMatcher for Vf2Matcher {
+    fn name(&self) -> &str {
+        "vf2"
+    }
+
+    fn match_candidates(&self, trips: &HashMap<String, Vec<ScheduleStop>>, vehicles: &HashMap<String, Vec<VehicleEvent>>) -> BTreeMap<String, Vec<TripMatch>> {
+        algorithm_vf2_part_1::match_trip_candidates(trips, vehicles, &self.params, &self.cost)
+    }
+
+    fn match_trip_to_vehicle(&self, trip_key: &str, stops: &[ScheduleStop], vehicle: &str, events: &[VehicleEvent]) -> Option<TripMatch> {
+        algorithm_vf2_part_1::match_trip_to_vehicle(trip_key, stops, vehicle, events, &self.cost, &self.params.for_trip(stops))
+    }
+}
+
+// Auswahl des Algorithmus auf der Kommandozeile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    Heuristic,
+    Vf2,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 2] = [Algorithm::Heuristic, Algorithm::Vf2];
+
+    // Name des Algorithmus in Ausgaben und Dateinamen (wie TripMatcher::name)
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Heuristic => "heuristic",
+            Algorithm::Vf2 => "vf2",
+        }
+    }
+
+    pub fn matcher(self, params: &MatchingParams, cost: &HeuristicCost) -> Box<dyn TripMatcher> {
+        match self {
+            Algorithm::Heuristic => Box::new(HeuristicMatcher { params: params.clone(), cost: *cost }),
+            Algorithm::Vf2 => Box::new(Vf2Matcher { params: params.clone(), cost: *cost }),
+        }
+    }
+
+    // Ergebnisdatei des Algorithmus
+    pub fn output_path(self, paths: &Paths) -> Result<&str, Box<dyn Error>> {
+        match self {
+            Algorithm::Heuristic => require(&paths.output_heuristic, "output_heuristic"),
+            Algorithm::Vf2 => require(&paths.output_vf2, "output_vf2"),
+        }
+    }
+
+    // Datei für die Zuordnung Fahrt -> Fahrzeug mit Bewertung, sofern angegeben
+    pub fn assignment_path(self, paths: &Paths) -> Option<&str> {
+        match self {
+            Algorithm::Heuristic => paths.zuordnung_heuristic.as_deref(),
+            Algorithm::Vf2 => paths.zuordnung.as_deref(),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// Toleranzprofile: die Toleranzen aus [tolerances] gelten für alle Fahrten, einzelne Profile überschreiben sie
+// für bestimmte Linien, Richtungen und Tageszeiten (z.B. großzügiger im Berufsverkehr als im Nachtverkehr)
+use std::error::Error;
+use serde::Deserialize;
+
+use crate::config::Tolerances;
+use crate::model::ScheduleStop;
+use crate::timestamp::TimeZone;
+
+// Toleranzen, die ein Profil überschreibt; nicht angegebene Werte bleiben unverändert
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ToleranceOverrides {
+    pub time_tolerance_seconds: Option<u64>,
+    pub early_tolerance_seconds: Option<u64>,
+    pub propagate_delay: Option<bool>,
+    pub candidate_radius_m: Option<f64>,
+    pub max_delay_seconds: Option<u64>,
+    pub match_distance_m: Option<f64>,
+    pub max_skipped_stops: Option<usize>,
+    pub max_search_states: Option<usize>,
+    pub evaluation_distance_m: Option<f64>,
+}
+
+impl ToleranceOverrides {
+    fn apply(&self, tolerances: &mut Tolerances) {
+        if let Some(value) = self.time_tolerance_seconds {
+            tolerances.time_tolerance_seconds = value;
+        }
+        if let Some(value) = self.early_tolerance_seconds {
+            tolerances.early_tolerance_seconds = value;
+        }
+        if let Some(value) = self.propagate_delay {
+            tolerances.propagate_delay = value;
+        }
+        if let Some(value) = self.candidate_radius_m {
+            tolerances.candidate_radius_m = value;
+        }
+        if let Some(value) = self.max_delay_seconds {
+            tolerances.max_delay_seconds = value;
+        }
+        if let Some(value) = self.match_distance_m {
+            tolerances.match_distance_m = value;
+        }
+        if let Some(value) = self.max_skipped_stops {
+            tolerances.max_skipped_stops = value;
+        }
+        if let Some(value) = self.max_search_states {
+            tolerances.max_search_states = value;
+        }
+        if let Some(value) = self.evaluation_distance_m {
+            tolerances.evaluation_distance_m = value;
+        }
+    }
+}
+
+// Ein Profil aus der Konfiguration ([[profiles]]); leere Bedingungen gelten für alle Fahrten
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ParamProfile {
+    pub name: Option<String>,
+    pub lines: Vec<u32>,           // Linien, leer = alle
+    pub richtung: Option<u32>,     // Richtung, fehlt = beide
+    pub time_band: Option<String>, // Zeitband der Fahrtanfangszeit "HH:MM-HH:MM", auch über Mitternacht
+    #[serde(flatten)]
+    pub overrides: ToleranceOverrides,
+}
+
+// Zeitband in Minuten nach Mitternacht, Ende ausgeschlossen
+#[derive(Debug, Clone, Copy)]
+pub struct TimeBand {
+    start: u32,
+    end: u32,
+}
+
+impl TimeBand {
+    pub fn parse(text: &str) -> Option<TimeBand> {
+        let (start, end) = text.split_once('-')?;
+        Some(TimeBand { start: minute_of_day(start.trim())?, end: minute_of_day(end.trim())? })
+    }
+
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            self.start <= minute && minute < self.end
+        } else {
+            // Zeitband über Mitternacht, z.B. 22:00-05:00
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+// Minuten nach Mitternacht aus "H:MM" oder "HH:MM:SS"; Zeiten nach 24 Uhr (Betriebstag) werden umgebrochen
+pub fn minute_of_day(text: &str) -> Option<u32> {
+    let mut parts = text.split(':');
+    let hours: u32 = parts.next()?.trim().parse().ok()?;
+    let minutes: u32 = parts.next()?.trim().parse().ok()?;
+    if minutes >= 60 {
+        return None;
+    }
+    Some((hours * 60 + minutes) % (24 * 60))
+}
+
+// Minute nach Mitternacht in Ortszeit eines Unix-Zeitstempels (z.B. ZEIT). None ohne Zeitstempel (0).
+pub fn local_minute(unix: u64, time_zone: TimeZone) -> Option<u32> {
+    if unix == 0 {
+        return None;
+    }
+    let offset_minutes = time_zone.offset_minutes(unix as i64) as i64;
+    Some((unix as i64 / 60 + offset_minutes).rem_euclid(24 * 60) as u32)
+}
+
+// Haltestelle mit der frühesten Soll-Zeit einer Fahrt; Haltestellen ohne ZEIT (0) nur, wenn keine andere vorhanden ist
+pub fn first_stop<'a>(stops: impl IntoIterator<Item = &'a ScheduleStop>) -> Option<&'a ScheduleStop> {
+    stops.into_iter().min_by_key(|stop| (stop.zeit == 0, stop.zeit))
+}
+
+// Profil mit geprüftem Zeitband
+#[derive(Debug, Clone)]
+struct Profile {
+    lines: Vec<u32>,
+    richtung: Option<u32>,
+    time_band: Option<TimeBand>,
+    overrides: ToleranceOverrides,
+}
+
+impl Profile {
+    // `start_minute` ist die Anfangszeit der Fahrt in Minuten nach Mitternacht (Ortszeit)
+    fn applies_to(&self, first: &ScheduleStop, start_minute: Option<u32>) -> bool {
+        (self.lines.is_empty() || self.lines.contains(&first.line))
+            && self.richtung.is_none_or(|richtung| richtung == first.richtung)
+            && self.time_band.is_none_or(|band| start_minute.is_some_and(|minute| band.contains(minute)))
+    }
+}
+
+// Parameter der Zuordnung und der Auswertung: Standardtoleranzen und Profile in der Reihenfolge der Konfiguration
+#[derive(Debug, Clone)]
+pub struct MatchingParams {
+    pub default: Tolerances,
+    profiles: Vec<Profile>,
+    time_zone: TimeZone,
+}
+
+impl MatchingParams {
+    pub fn new(default: Tolerances, profiles: &[ParamProfile], time_zone: TimeZone) -> Result<Self, Box<dyn Error>> {
+        let profiles = profiles.iter().enumerate().map(|(position, profile)| {
+            let time_band = match &profile.time_band {
+                Some(text) => Some(TimeBand::parse(text).ok_or_else(|| {
+                    let name = profile.name.clone().unwrap_or_else(|| format!("Nr. {}", position + 1));
+                    format!("Profil {}: Zeitband '{}' ungültig, erwartet HH:MM-HH:MM", name, text)
+                })?),
+                None => None,
+            };
+            Ok(Profile {
+                lines: profile.lines.clone(),
+                richtung: profile.richtung,
+                time_band,
+                overrides: profile.overrides.clone(),
+            })
+        }).collect::<Result<Vec<Profile>, Box<dyn Error>>>()?;
+        Ok(MatchingParams { default, profiles, time_zone })
+    }
+
+    // Toleranzen einer Fahrt: Linie und Richtung der Haltestellen, Anfangszeit ist die früheste Soll-Zeit (ZEIT);
+    // treffen mehrere Profile zu, gewinnt das spätere
+    pub fn for_trip<'a>(&self, stops: impl IntoIterator<Item = &'a ScheduleStop>) -> Tolerances {
+        let mut tolerances = self.default;
+        if let Some(first) = first_stop(stops) {
+            let start_minute = self.local_minute(first.zeit);
+            for profile in self.profiles.iter().filter(|profile| profile.applies_to(first, start_minute)) {
+                profile.overrides.apply(&mut tolerances);
+            }
+        }
+        tolerances
+    }
+
+    // Minute nach Mitternacht in Ortszeit einer Soll-Zeit (ZEIT)
+    pub fn local_minute(&self, zeit: u64) -> Option<u32> {
+        local_minute(zeit, self.time_zone)
+    }
+}
@@ -0,0 +1,225 @@
+// Gemeinsames Datenmodell für Fahrplan- und Fahrzeugdaten
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use csv::WriterBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{self, InputOptions, SCHEDULE_COLUMNS, VEHICLE_COLUMNS};
+use crate::timestamp::Timestamp;
+
+// Struktur für eine Haltestelle im Fahrplan (eine Zeile der Schedule-CSV)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleStop {
+    #[serde(rename = "SCHEDULE_ID")]
+    pub schedule_id: String,
+    #[serde(rename = "DATUM")]
+    pub datum: String,
+    #[serde(rename = "FRT_FID")]
+    pub frt_fid: u32,
+    #[serde(rename = "FRT_START")]
+    pub frt_start: u32,
+    #[serde(rename = "LINE")]
+    pub line: u32,
+    #[serde(rename = "RICHTUNG")]
+    pub richtung: u32,
+    #[serde(rename = "VARIANTE")]
+    pub varianten: u32,
+    #[serde(rename = "UMLAUF")]
+    pub umlauf: u32,
+    #[serde(rename = "LFD_NR")]
+    pub lfnr: u32,
+    #[serde(rename = "ANKUNFT")]
+    pub ankunft: u32,
+    #[serde(rename = "ABFAHRT")]
+    pub abfahrt: u32,
+    #[serde(rename = "ZEITPKT")]
+    pub zeitpkt: String,
+    #[serde(rename = "ZEIT")]
+    pub zeit: u64,
+    #[serde(rename = "ORT_NR")]
+    pub ort_nr: u32,
+    #[serde(rename = "ORT_NAME")]
+    pub ort_name: String,
+    #[serde(rename = "LON")]
+    pub lon: f64,
+    #[serde(rename = "LAT")]
+    pub lat: f64,
+    #[serde(rename = "X")]
+    pub x: f64,
+    #[serde(rename = "Y")]
+    pub y: f64,
+    #[serde(rename = "FAHRT_START")]
+    pub fahrt_start: String,
+    #[serde(rename = "FAHRT_ENDE")]
+    pub fahrt_ende: String,
+    #[serde(rename = "WKT")]
+    pub wkt: String,
+}
+
+// Struktur für ein Fahrzeugereignis (eine Zeile der Rohdaten-CSV); `zeit` und `zeit_next` begrenzen den Halt
+// (Türöffnung bis zum nächsten Ereignis) und werden beim Einlesen in Zeitstempel umgewandelt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawVehicleEvent")]
+pub struct VehicleEvent {
+    pub vehicle: String,
+    pub datum: String,
+    pub zeit: Timestamp,
+    pub zeit_next: Option<Timestamp>, // Fehlt beim letzten Ereignis eines Fahrzeugs
+    pub unixzeit: u64,
+    pub lat: f64,
+    pub lon: f64,
+    pub x: f64,
+    pub y: f64,
+    pub typ: String,
+    pub einsteiger: u32,
+    pub aussteiger: u32,
+    pub wkt: String,
+}
+
+// Fahrzeugereignis mit den Zeitangaben als Text, wie es in der CSV steht
+#[derive(Deserialize)]
+struct RawVehicleEvent {
+    vehicle: String,
+    datum: String,
+    zeit: String,
+    zeit_next: String,
+    unixzeit: u64,
+    lat: f64,
+    lon: f64,
+    x: f64,
+    y: f64,
+    typ: String,
+    einsteiger: u32,
+    aussteiger: u32,
+    wkt: String,
+}
+
+impl TryFrom<RawVehicleEvent> for VehicleEvent {
+    type Error = String;
+
+    fn try_from(raw: RawVehicleEvent) -> Result<Self, Self::Error> {
+        let zeit = Timestamp::parse(&raw.zeit, &raw.datum)
+            .ok_or_else(|| format!("zeit: ungültige Zeitangabe '{}'", raw.zeit))?;
+        let zeit_next = match raw.zeit_next.trim() {
+            "" => None,
+            value => Some(Timestamp::parse(value, &raw.datum).ok_or_else(|| format!("zeit_next: ungültige Zeitangabe '{}'", value))?),
+        };
+        Ok(VehicleEvent {
+            vehicle: raw.vehicle,
+            datum: raw.datum,
+            zeit,
+            zeit_next,
+            unixzeit: raw.unixzeit,
+            lat: raw.lat,
+            lon: raw.lon,
+            x: raw.x,
+            y: raw.y,
+            typ: raw.typ,
+            einsteiger: raw.einsteiger,
+            aussteiger: raw.aussteiger,
+            wkt: raw.wkt,
+        })
+    }
+}
+
+// Implementierung von Eq und Hash, damit die Strukturen als Schlüssel in HashMaps dienen können
+impl Eq for ScheduleStop {}
+
+impl Hash for ScheduleStop {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Felder die für die Hashberechnung nötig sind
+        self.schedule_id.hash(state);
+        self.datum.hash(state);
+    }
+}
+
+impl Eq for VehicleEvent {}
+
+impl Hash for VehicleEvent {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.vehicle.hash(state);
+        self.unixzeit.hash(state);
+    }
+}
+
+// Schreibt alle Datensätze inklusive Kopfzeile in eine CSV-Datei
+fn write_records<T: Serialize>(file_path: &Path, records: &[T]) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = WriterBuilder::new()
+        .delimiter(b';')
+        .from_path(file_path)?;
+
+    for record in records {
+        csv_writer.serialize(record)?;
+    }
+
+    csv_writer.flush()?;
+    Ok(())
+}
+
+// Liefert alle CSV-Dateien eines Ordners
+fn csv_files_in_folder(folder_path: &Path) -> Result<Vec<std::path::PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(folder_path)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().unwrap_or_default() == "csv" {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// Funktion zum Lesen einer Fahrplan-CSV-Datei mit Spaltenaliasen und Fehlerbehandlung
+pub fn read_schedule_stops_with<P: AsRef<Path>>(file_path: P, options: &InputOptions) -> Result<Vec<ScheduleStop>, Box<dyn Error>> {
+    schema::read_mapped_records(file_path.as_ref(), SCHEDULE_COLUMNS, options)
+}
+
+// Funktion zum Schreiben von Fahrplanhaltestellen in eine CSV-Datei
+pub fn write_schedule_stops<P: AsRef<Path>>(file_path: P, stops: &[ScheduleStop]) -> Result<(), Box<dyn Error>> {
+    write_records(file_path.as_ref(), stops)
+}
+
+// Funktion zum Lesen einer Fahrzeug-CSV-Datei mit Spaltenaliasen und Fehlerbehandlung
+pub fn read_vehicle_events_with<P: AsRef<Path>>(file_path: P, options: &InputOptions) -> Result<Vec<VehicleEvent>, Box<dyn Error>> {
+    schema::read_mapped_records(file_path.as_ref(), VEHICLE_COLUMNS, options)
+}
+
+// Funktion zum Schreiben von Fahrzeugereignissen in eine CSV-Datei
+pub fn write_vehicle_events<P: AsRef<Path>>(file_path: P, events: &[VehicleEvent]) -> Result<(), Box<dyn Error>> {
+    write_records(file_path.as_ref(), events)
+}
+
+// Funktion zum Lesen einer Ergebnisdatei (Fahrplanhaltestelle gefolgt vom zugeordneten Fahrzeugereignis)
+pub fn read_matched_stops<P: AsRef<Path>>(file_path: P, options: &InputOptions) -> Result<Vec<(ScheduleStop, VehicleEvent)>, Box<dyn Error>> {
+    let columns = [SCHEDULE_COLUMNS, VEHICLE_COLUMNS].concat();
+    schema::read_mapped_records(file_path.as_ref(), &columns, options)
+}
+
+// Funktion zum Schreiben einer Ergebnisdatei
+pub fn write_matched_stops<P: AsRef<Path>>(file_path: P, matched: &[(ScheduleStop, VehicleEvent)]) -> Result<(), Box<dyn Error>> {
+    write_records(file_path.as_ref(), matched)
+}
+
+// Funktion zum Einlesen aller Fahrtdateien eines Ordners, Schlüssel ist der Dateiname ohne Endung
+pub fn read_schedule_trips_from_folder<P: AsRef<Path>>(folder_path: P, options: &InputOptions) -> Result<HashMap<String, Vec<ScheduleStop>>, Box<dyn Error>> {
+    let mut trips: HashMap<String, Vec<ScheduleStop>> = HashMap::new();
+    for file_path in csv_files_in_folder(folder_path.as_ref())? {
+        let file_stem = file_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        trips.entry(file_stem).or_default().extend(read_schedule_stops_with(&file_path, options)?);
+    }
+    Ok(trips)
+}
+
+// Funktion zum Einlesen aller Fahrzeugdateien eines Ordners, Schlüssel ist die Fahrzeugnummer
+pub fn read_vehicle_events_from_folder<P: AsRef<Path>>(folder_path: P, options: &InputOptions) -> Result<HashMap<String, Vec<VehicleEvent>>, Box<dyn Error>> {
+    let mut vehicles: HashMap<String, Vec<VehicleEvent>> = HashMap::new();
+    for file_path in csv_files_in_folder(folder_path.as_ref())? {
+        for event in read_vehicle_events_with(&file_path, options)? {
+            vehicles.entry(event.vehicle.clone()).or_default().push(event);
+        }
+    }
+    Ok(vehicles)
+}
@@ -0,0 +1,186 @@
+// Fahrgastströme (OD-Matrix) je Fahrt: aus den ausgeglichenen Ein- und Aussteigern je Haltestelle wird die Matrix
+// Einstieg -> Ausstieg mit iterativem proportionalem Anpassen (IPF) geschätzt. Der Startwert eines Paares hängt von der
+// Fahrtweite entlang der Fahrt ab: sehr kurze und sehr lange Fahrten sind seltener.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use csv::WriterBuilder;
+use serde::Deserialize;
+
+use crate::event_index::haversine_m;
+use crate::matcher::MatchedTrips;
+use crate::model::{ScheduleStop, VehicleEvent};
+use crate::passenger_balancing::BalancedTrip;
+use crate::time_bands::TimeBands;
+
+// Einstellungen der Schätzung ([od] in der Konfiguration)
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct OdConfig {
+    pub distance_decay_m: f64, // Startwert = Fahrtweite * exp(-Fahrtweite / distance_decay_m)
+    pub max_iterations: usize, // Höchstzahl der IPF-Durchläufe je Fahrt
+    pub tolerance: f64,        // Abbruch, wenn alle Zeilen- und Spaltensummen höchstens so weit abweichen (Fahrgäste)
+    pub min_flow: f64,         // Kleinere Ströme werden nicht ausgegeben
+}
+
+impl Default for OdConfig {
+    fn default() -> Self {
+        OdConfig { distance_decay_m: 3000.0, max_iterations: 1000, tolerance: 0.01, min_flow: 0.01 }
+    }
+}
+
+// Geschätzte Matrix einer Fahrt; `flows[i][j]` = Fahrgäste von Haltestelle i nach j (nur j > i)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripOdMatrix {
+    pub trip_key: String,
+    pub line: u32,
+    pub richtung: u32,
+    pub time_band: String,
+    pub stops: Vec<(u32, String)>, // ORT_NR und ORT_NAME in Fahrtreihenfolge
+    pub flows: Vec<Vec<f64>>,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+// Schätzt die Matrix einer Fahrt aus den ausgeglichenen Werten (gleiche Haltestellen und Reihenfolge wie `stops`)
+pub fn estimate_trip(stops: &[(ScheduleStop, VehicleEvent)], balanced: &BalancedTrip, time_bands: &TimeBands, config: &OdConfig) -> TripOdMatrix {
+    let count = stops.len();
+    let boardings: Vec<f64> = balanced.stops.iter().map(|stop| stop.boardings).collect();
+    let alightings: Vec<f64> = balanced.stops.iter().map(|stop| stop.alightings).collect();
+
+    // Fahrtweite entlang der Fahrt bis zu jeder Haltestelle
+    let mut position_m = vec![0.0; count];
+    for index in 1..count {
+        let (previous, _) = &stops[index - 1];
+        let (stop, _) = &stops[index];
+        position_m[index] = position_m[index - 1] + haversine_m(previous.lat, previous.lon, stop.lat, stop.lon);
+    }
+
+    // Startwerte: nur Fahrten in Fahrtrichtung; ohne Entfernung (gleiche Position) ein kleiner Wert statt 0
+    let mut flows = vec![vec![0.0; count]; count];
+    for origin in 0..count {
+        for destination in origin + 1..count {
+            let distance_m = (position_m[destination] - position_m[origin]).max(1.0);
+            flows[origin][destination] = distance_m * (-distance_m / config.distance_decay_m.max(1.0)).exp();
+        }
+    }
+
+    // IPF: abwechselnd Zeilen auf die Einsteiger und Spalten auf die Aussteiger skalieren
+    let mut iterations = 0;
+    let mut converged = false;
+    while iterations < config.max_iterations {
+        iterations += 1;
+        for (origin, row) in flows.iter_mut().enumerate() {
+            let sum: f64 = row.iter().sum();
+            if sum > 0.0 {
+                let factor = boardings[origin] / sum;
+                row.iter_mut().for_each(|flow| *flow *= factor);
+            }
+        }
+        for (destination, target) in alightings.iter().enumerate() {
+            let sum: f64 = flows.iter().map(|row| row[destination]).sum();
+            if sum > 0.0 {
+                let factor = target / sum;
+                flows.iter_mut().for_each(|row| row[destination] *= factor);
+            }
+        }
+        let row_error = flows.iter().zip(&boardings).map(|(row, target)| (row.iter().sum::<f64>() - target).abs());
+        let column_error = alightings.iter().enumerate().map(|(destination, target)| (flows.iter().map(|row| row[destination]).sum::<f64>() - target).abs());
+        if row_error.chain(column_error).all(|error| error <= config.tolerance) {
+            converged = true;
+            break;
+        }
+    }
+
+    let first = stops.first().map(|(stop, _)| stop);
+    TripOdMatrix {
+        trip_key: balanced.trip_key.clone(),
+        line: first.map_or(0, |stop| stop.line),
+        richtung: first.map_or(0, |stop| stop.richtung),
+        time_band: first.map_or_else(|| "unbekannt".to_string(), |stop| time_bands.trip_label(stop)),
+        stops: stops.iter().map(|(stop, _)| (stop.ort_nr, stop.ort_name.clone())).collect(),
+        flows,
+        iterations,
+        converged,
+    }
+}
+
+// Schätzt die Matrizen aller ausgeglichenen Fahrten
+pub fn estimate_trips(trips: &MatchedTrips, balanced: &[BalancedTrip], time_bands: &TimeBands, config: &OdConfig) -> Vec<TripOdMatrix> {
+    balanced.iter()
+        .filter_map(|balanced_trip| {
+            trips.get(&balanced_trip.trip_key).map(|stops| estimate_trip(stops, balanced_trip, time_bands, config))
+        })
+        .collect()
+}
+
+// Ströme je Linie und Zeitband: (Linie, Zeitband, von ORT_NR, nach ORT_NR) -> (von ORT_NAME, nach ORT_NAME, Fahrgäste)
+pub type LineFlows = BTreeMap<(u32, String, u32, u32), (String, String, f64)>;
+
+pub fn aggregate_by_line(matrices: &[TripOdMatrix]) -> LineFlows {
+    let mut aggregated = LineFlows::new();
+    for matrix in matrices {
+        for (origin, row) in matrix.flows.iter().enumerate() {
+            for (destination, flow) in row.iter().enumerate().filter(|(_, flow)| **flow > 0.0) {
+                let (from_nr, from_name) = &matrix.stops[origin];
+                let (to_nr, to_name) = &matrix.stops[destination];
+                aggregated.entry((matrix.line, matrix.time_band.clone(), *from_nr, *to_nr))
+                    .or_insert_with(|| (from_name.clone(), to_name.clone(), 0.0))
+                    .2 += flow;
+            }
+        }
+    }
+    aggregated
+}
+
+// Schreibt die Ströme je Fahrt ({algorithmus}_od_fahrten.csv, mit KONVERGIERT 1/0 und der Zahl der IPF-Durchläufe) und
+// je Linie und Zeitband ({algorithmus}_od_linien.csv) in den Ordner `output_folder`
+pub fn write_od(algorithm: &str, matrices: &[TripOdMatrix], config: &OdConfig, output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let folder = Path::new(output_folder);
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_od_fahrten.csv", algorithm)))?;
+    writer.write_record(["FAHRT", "LINIE", "RICHTUNG", "ZEITBAND", "VON_ORT_NR", "VON_ORT_NAME", "NACH_ORT_NR", "NACH_ORT_NAME", "FAHRGAESTE", "KONVERGIERT", "ITERATIONEN"])?;
+    for matrix in matrices {
+        for (origin, row) in matrix.flows.iter().enumerate() {
+            for (destination, flow) in row.iter().enumerate().filter(|(_, flow)| **flow >= config.min_flow) {
+                let (from_nr, from_name) = &matrix.stops[origin];
+                let (to_nr, to_name) = &matrix.stops[destination];
+                writer.write_record([
+                    matrix.trip_key.clone(),
+                    matrix.line.to_string(),
+                    matrix.richtung.to_string(),
+                    matrix.time_band.clone(),
+                    from_nr.to_string(),
+                    from_name.clone(),
+                    to_nr.to_string(),
+                    to_name.clone(),
+                    format!("{:.2}", flow),
+                    u8::from(matrix.converged).to_string(),
+                    matrix.iterations.to_string(),
+                ])?;
+            }
+        }
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_od_linien.csv", algorithm)))?;
+    writer.write_record(["LINIE", "ZEITBAND", "VON_ORT_NR", "VON_ORT_NAME", "NACH_ORT_NR", "NACH_ORT_NAME", "FAHRGAESTE"])?;
+    for ((line, time_band, from_nr, to_nr), (from_name, to_name, flow)) in aggregate_by_line(matrices) {
+        if flow < config.min_flow {
+            continue;
+        }
+        writer.write_record([
+            line.to_string(),
+            time_band,
+            from_nr.to_string(),
+            from_name,
+            to_nr.to_string(),
+            to_name,
+            format!("{:.2}", flow),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
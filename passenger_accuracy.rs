@@ -0,0 +1,221 @@
+// Genauigkeit der Fahrgastzahlen: Einsteiger und Aussteiger der zugeordneten Fahrzeugereignisse gegen die Zähldaten,
+// je Haltestelle und je Fahrt. Die Fahrgastzahlen sind das eigentliche Ergebnis, die Zuordnung nur der Weg dorthin.
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use csv::WriterBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{ScheduleStop, VehicleEvent};
+use crate::schema::{self, InputOptions, SchemaError};
+
+// Fahrgastzahlen einer Haltestelle in den Vergleichsdaten
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReferenceCount {
+    schedule_id: String,
+    einsteiger: u32,
+    aussteiger: u32,
+}
+
+// Spalten der Vergleichsdatei in der Reihenfolge der Felder von ReferenceCount
+pub const COUNT_COLUMNS: &[&str] = &["SCHEDULE_ID", "EINSTEIGER", "AUSSTEIGER"];
+
+// Liest die Fahrgastzahlen der Vergleichsdaten; None, wenn die Datei keine Spalten EINSTEIGER und AUSSTEIGER hat
+pub fn load_reference_counts(filename: &str, options: &InputOptions) -> Result<Option<Vec<ReferenceCount>>, Box<dyn Error>> {
+    match schema::read_mapped_records(Path::new(filename), COUNT_COLUMNS, options) {
+        Ok(counts) => Ok(Some(counts)),
+        Err(err) if err.is::<SchemaError>() => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+// Fahrgastzahlen einer Fahrt im Ergebnis und in den Vergleichsdaten
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TripCounts {
+    pub trip_key: String,
+    pub reference_boardings: u64,
+    pub assigned_boardings: u64,
+    pub reference_alightings: u64,
+    pub assigned_alightings: u64,
+}
+
+impl TripCounts {
+    // Relative Abweichung der Einsteiger; None, wenn in den Vergleichsdaten niemand eingestiegen ist
+    pub fn boardings_relative_error(&self) -> Option<f64> {
+        relative_error(self.assigned_boardings, self.reference_boardings)
+    }
+
+    pub fn alightings_relative_error(&self) -> Option<f64> {
+        relative_error(self.assigned_alightings, self.reference_alightings)
+    }
+
+    // Einsteiger und Aussteiger weichen höchstens um den Anteil `tolerance` ab
+    pub fn within(&self, tolerance: f64) -> bool {
+        within(self.assigned_boardings, self.reference_boardings, tolerance)
+            && within(self.assigned_alightings, self.reference_alightings, tolerance)
+    }
+}
+
+// Fehlermaße einer Größe (Einsteiger oder Aussteiger); Abweichung = Ergebnis - Vergleichsdaten
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CountErrors {
+    pub stop_mae: f64,                     // Mittlerer absoluter Fehler je Haltestelle
+    pub stop_bias: f64,                    // Mittlere Abweichung je Haltestelle (positiv = zu viele)
+    pub trip_mae: f64,                     // Mittlerer absoluter Fehler der Summe je Fahrt
+    pub trip_bias: f64,                    // Mittlere Abweichung der Summe je Fahrt
+    pub trip_mean_relative_error: f64,     // Mittlere absolute relative Abweichung je Fahrt (Fahrten mit Zählwert > 0)
+    pub trips_within_tolerance_share: f64, // Anteil der Fahrten mit höchstens `tolerance` relativer Abweichung
+}
+
+// Ergebnis der Auswertung der Fahrgastzahlen
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PassengerReport {
+    pub stops: usize,                  // Haltestellen der Fahrten aus den Vergleichsdaten
+    pub trips: usize,                  // Fahrten in den Vergleichsdaten
+    pub tolerance: f64,                // Zulässige relative Abweichung je Fahrt
+    pub trips_within_tolerance: usize, // Fahrten, deren Einsteiger und Aussteiger beide innerhalb der Toleranz liegen
+    pub boardings: CountErrors,
+    pub alightings: CountErrors,
+    #[serde(skip)]
+    pub trip_counts: Vec<TripCounts>,  // Nach Fahrt sortiert
+}
+
+impl PassengerReport {
+    pub fn print(&self) {
+        println!("Fahrgastzahlen: {} Fahrten, {} Haltestellen aus den Vergleichsdaten.", self.trips, self.stops);
+        for (name, errors) in [("Einsteiger", &self.boardings), ("Aussteiger", &self.alightings)] {
+            println!("{}: MAE {:.2} je Haltestelle, {:.2} je Fahrt; Bias {:+.2} je Haltestelle, {:+.2} je Fahrt; relative Abweichung je Fahrt {:.2}%; innerhalb ±{:.0}%: {:.2}% der Fahrten",
+                name, errors.stop_mae, errors.trip_mae, errors.stop_bias, errors.trip_bias, errors.trip_mean_relative_error * 100.0,
+                self.tolerance * 100.0, errors.trips_within_tolerance_share * 100.0);
+        }
+        println!("Fahrten mit Einsteigern und Aussteigern innerhalb ±{:.0}%: {} von {}", self.tolerance * 100.0, self.trips_within_tolerance, self.trips);
+    }
+}
+
+// Vergleicht die Fahrgastzahlen des Ergebnisses mit den Vergleichsdaten. Berücksichtigt werden die Fahrten der
+// Vergleichsdaten mit allen ihren Haltestellen aus beiden Seiten; fehlt eine Haltestelle auf einer Seite, zählt sie dort
+// mit 0 Fahrgästen. Je SCHEDULE_ID zählt die erste Zeile.
+pub fn evaluate_counts(matched: &[(ScheduleStop, VehicleEvent)], reference: &[ReferenceCount], trips: &HashMap<String, Vec<ScheduleStop>>, tolerance: f64) -> PassengerReport {
+    // SCHEDULE_ID -> Fahrt
+    let trip_of: HashMap<&str, &str> = trips.iter()
+        .flat_map(|(trip_key, stops)| stops.iter().map(move |stop| (stop.schedule_id.as_str(), trip_key.as_str())))
+        .collect();
+
+    let mut reference_by_stop: HashMap<&str, (u32, u32)> = HashMap::new();
+    for count in reference {
+        if let Entry::Vacant(entry) = reference_by_stop.entry(count.schedule_id.as_str()) {
+            entry.insert((count.einsteiger, count.aussteiger));
+        }
+    }
+    let mut output_by_stop: HashMap<&str, (u32, u32)> = HashMap::new();
+    for (stop, event) in matched {
+        if let Entry::Vacant(entry) = output_by_stop.entry(stop.schedule_id.as_str()) {
+            entry.insert((event.einsteiger, event.aussteiger));
+        }
+    }
+
+    // Fahrten der Vergleichsdaten und ihre Haltestellen aus beiden Seiten
+    let mut trip_stops: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for schedule_id in reference_by_stop.keys() {
+        if let Some(trip_key) = trip_of.get(schedule_id) {
+            trip_stops.entry(trip_key).or_default().push(schedule_id);
+        }
+    }
+    for schedule_id in output_by_stop.keys() {
+        if let Some(stops) = trip_of.get(schedule_id).and_then(|trip_key| trip_stops.get_mut(trip_key)) {
+            if !reference_by_stop.contains_key(schedule_id) {
+                stops.push(schedule_id);
+            }
+        }
+    }
+
+    let mut report = PassengerReport { tolerance, ..Default::default() };
+    let mut boarding_errors = Vec::new();
+    let mut alighting_errors = Vec::new();
+    for (trip_key, stops) in &trip_stops {
+        let mut counts = TripCounts { trip_key: trip_key.to_string(), ..Default::default() };
+        for schedule_id in stops {
+            let (reference_in, reference_out) = reference_by_stop.get(schedule_id).copied().unwrap_or((0, 0));
+            let (assigned_in, assigned_out) = output_by_stop.get(schedule_id).copied().unwrap_or((0, 0));
+            boarding_errors.push(assigned_in as f64 - reference_in as f64);
+            alighting_errors.push(assigned_out as f64 - reference_out as f64);
+            counts.reference_boardings += reference_in as u64;
+            counts.assigned_boardings += assigned_in as u64;
+            counts.reference_alightings += reference_out as u64;
+            counts.assigned_alightings += assigned_out as u64;
+        }
+        report.stops += stops.len();
+        report.trip_counts.push(counts);
+    }
+    report.trips = report.trip_counts.len();
+    report.trips_within_tolerance = report.trip_counts.iter().filter(|counts| counts.within(tolerance)).count();
+
+    let boarding_totals: Vec<(u64, u64)> = report.trip_counts.iter().map(|counts| (counts.assigned_boardings, counts.reference_boardings)).collect();
+    let alighting_totals: Vec<(u64, u64)> = report.trip_counts.iter().map(|counts| (counts.assigned_alightings, counts.reference_alightings)).collect();
+    report.boardings = count_errors(&boarding_errors, &boarding_totals, tolerance);
+    report.alightings = count_errors(&alighting_errors, &alighting_totals, tolerance);
+    report
+}
+
+// Fehlermaße aus den Abweichungen je Haltestelle und den Summen (Ergebnis, Vergleichsdaten) je Fahrt
+fn count_errors(stop_errors: &[f64], trip_totals: &[(u64, u64)], tolerance: f64) -> CountErrors {
+    let trip_errors: Vec<f64> = trip_totals.iter().map(|&(assigned, reference)| assigned as f64 - reference as f64).collect();
+    let relative_errors: Vec<f64> = trip_totals.iter()
+        .filter_map(|&(assigned, reference)| relative_error(assigned, reference))
+        .map(f64::abs)
+        .collect();
+    CountErrors {
+        stop_mae: mean(stop_errors.iter().map(|error| error.abs())),
+        stop_bias: mean(stop_errors.iter().copied()),
+        trip_mae: mean(trip_errors.iter().map(|error| error.abs())),
+        trip_bias: mean(trip_errors.iter().copied()),
+        trip_mean_relative_error: mean(relative_errors.into_iter()),
+        trips_within_tolerance_share: if trip_totals.is_empty() {
+            0.0
+        } else {
+            trip_totals.iter().filter(|&&(assigned, reference)| within(assigned, reference, tolerance)).count() as f64 / trip_totals.len() as f64
+        },
+    }
+}
+
+fn relative_error(assigned: u64, reference: u64) -> Option<f64> {
+    (reference > 0).then(|| (assigned as f64 - reference as f64) / reference as f64)
+}
+
+// Ohne Fahrgäste in den Vergleichsdaten ist nur 0 im Ergebnis richtig
+fn within(assigned: u64, reference: u64, tolerance: f64) -> bool {
+    relative_error(assigned, reference).map_or(assigned == 0, |error| error.abs() <= tolerance)
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+// Schreibt die Fahrgastzahlen je Fahrt nach {algorithmus}_fahrgastzahlen.csv im Ordner `output_folder`
+pub fn write_trip_counts(algorithm: &str, report: &PassengerReport, output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let output_file = Path::new(output_folder).join(format!("{}_fahrgastzahlen.csv", algorithm));
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(output_file)?;
+    writer.write_record([
+        "FAHRT", "EINSTEIGER_VERGLEICH", "EINSTEIGER_ERGEBNIS", "ABWEICHUNG_EINSTEIGER", "AUSSTEIGER_VERGLEICH",
+        "AUSSTEIGER_ERGEBNIS", "ABWEICHUNG_AUSSTEIGER", "INNERHALB_TOLERANZ",
+    ])?;
+    let format_error = |error: Option<f64>| error.map(|error| format!("{:.4}", error)).unwrap_or_default();
+    for counts in &report.trip_counts {
+        writer.write_record([
+            counts.trip_key.clone(),
+            counts.reference_boardings.to_string(),
+            counts.assigned_boardings.to_string(),
+            format_error(counts.boardings_relative_error()),
+            counts.reference_alightings.to_string(),
+            counts.assigned_alightings.to_string(),
+            format_error(counts.alightings_relative_error()),
+            (counts.within(report.tolerance) as u8).to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,207 @@
+// Ausgleich der Fahrgastzahlen je Fahrt: gezählte Einsteiger und Aussteiger einer Fahrt sind fast nie gleich. Beide
+// Summen werden auf ihren Mittelwert korrigiert und die Besetzung darf nirgends negativ werden. Die Rohwerte bleiben
+// neben den ausgeglichenen Werten erhalten.
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use clap::ValueEnum;
+use csv::WriterBuilder;
+use serde::Deserialize;
+
+use crate::event_index::haversine_m;
+use crate::matcher::MatchedTrips;
+use crate::matching_params::MatchingParams;
+use crate::model::{ScheduleStop, VehicleEvent};
+use crate::trip_score::normalized_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BalancingMethod {
+    // Korrektur je Haltestelle im Verhältnis zum Zählwert
+    Proportional,
+    // Korrektur im Verhältnis zum Zählwert geteilt durch die Zuverlässigkeit der Zählung; unsicher zugeordnete
+    // Ereignisse (weit von Haltestelle oder Soll-Zeit entfernt) werden stärker korrigiert
+    Weighted,
+}
+
+// Einstellungen des Ausgleichs ([balancing] in der Konfiguration)
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct BalancingConfig {
+    pub method: BalancingMethod,
+}
+
+impl Default for BalancingConfig {
+    fn default() -> Self {
+        BalancingConfig { method: BalancingMethod::Proportional }
+    }
+}
+
+// Roh- und ausgeglichene Werte einer Haltestelle
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalancedStop {
+    pub schedule_id: String,
+    pub lfnr: u32,
+    pub ort_nr: u32,
+    pub ort_name: String,
+    pub raw_boardings: u32,
+    pub raw_alightings: u32,
+    pub reliability: f64, // Zuverlässigkeit der Zählung (1 = Ereignis genau an Haltestelle und Soll-Zeit)
+    pub boardings: f64,
+    pub alightings: f64,
+    pub load: f64,        // Ausgeglichene Besetzung nach der Haltestelle
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalancedTrip {
+    pub trip_key: String,
+    pub vehicle: String,
+    pub stops: Vec<BalancedStop>,
+    pub raw_boardings: u64,
+    pub raw_alightings: u64,
+    pub total: f64, // Einsteiger = Aussteiger nach dem Ausgleich
+}
+
+impl BalancedTrip {
+    // Mindestens eine Haltestelle hat nach dem Ausgleich andere Werte als gezählt
+    pub fn was_corrected(&self) -> bool {
+        self.stops.iter().any(|stop| {
+            (stop.boardings - stop.raw_boardings as f64).abs() > 1e-9 || (stop.alightings - stop.raw_alightings as f64).abs() > 1e-9
+        })
+    }
+}
+
+// Gleicht die Fahrgastzahlen einer Fahrt aus; die Haltestellen müssen nach LFD_NR sortiert sein
+pub fn balance_trip(trip_key: &str, stops: &[(ScheduleStop, VehicleEvent)], method: BalancingMethod, params: &MatchingParams) -> BalancedTrip {
+    let tolerances = params.for_trip(stops.iter().map(|(stop, _)| stop));
+    let reliabilities: Vec<f64> = stops.iter().map(|(stop, event)| match method {
+        BalancingMethod::Proportional => 1.0,
+        BalancingMethod::Weighted => {
+            let distance_m = haversine_m(stop.lat, stop.lon, event.lat, event.lon);
+            let time_difference = (stop.zeit as i64 - event.unixzeit as i64).unsigned_abs();
+            1.0 / (1.0 + normalized_error(distance_m, time_difference, &tolerances))
+        }
+    }).collect();
+
+    let raw_boardings: Vec<f64> = stops.iter().map(|(_, event)| event.einsteiger as f64).collect();
+    let raw_alightings: Vec<f64> = stops.iter().map(|(_, event)| event.aussteiger as f64).collect();
+    let boarding_total: f64 = raw_boardings.iter().sum();
+    let alighting_total: f64 = raw_alightings.iter().sum();
+
+    // Einsteiger an der letzten und Aussteiger an der ersten Haltestelle sind Zählfehler und werden verworfen
+    let last = stops.len().saturating_sub(1);
+    let mut counted_boardings = raw_boardings.clone();
+    let mut counted_alightings = raw_alightings.clone();
+    if stops.len() > 1 {
+        counted_boardings[last] = 0.0;
+        counted_alightings[0] = 0.0;
+    }
+    let total = (counted_boardings.iter().sum::<f64>() + counted_alightings.iter().sum::<f64>()) / 2.0;
+
+    // Summen auf den Mittelwert korrigieren
+    let boardings = distribute(&counted_boardings, &reliabilities, total, |index| index < last);
+    let mut alightings = distribute(&counted_alightings, &reliabilities, total, |index| index > 0);
+
+    // Erst aussteigen, dann einsteigen: an einer Haltestelle können höchstens so viele Fahrgäste aussteigen, wie
+    // ankommen. Überzählige Aussteiger werden auf die folgenden Haltestellen verschoben, an der letzten Haltestelle
+    // steigen alle verbliebenen Fahrgäste aus.
+    let mut load = 0.0;
+    let mut carry = 0.0;
+    let mut loads = Vec::with_capacity(stops.len());
+    for index in 0..stops.len() {
+        let wanted = alightings[index] + carry;
+        if index == last {
+            alightings[index] = load + boardings[index];
+        } else if wanted > load {
+            alightings[index] = load;
+            carry = wanted - load;
+        } else {
+            alightings[index] = wanted;
+            carry = 0.0;
+        }
+        load += boardings[index] - alightings[index];
+        loads.push(load);
+    }
+
+    BalancedTrip {
+        trip_key: trip_key.to_string(),
+        vehicle: stops.first().map(|(_, event)| event.vehicle.clone()).unwrap_or_default(),
+        stops: stops.iter().enumerate().map(|(index, (stop, event))| BalancedStop {
+            schedule_id: stop.schedule_id.clone(),
+            lfnr: stop.lfnr,
+            ort_nr: stop.ort_nr,
+            ort_name: stop.ort_name.clone(),
+            raw_boardings: event.einsteiger,
+            raw_alightings: event.aussteiger,
+            reliability: reliabilities[index],
+            boardings: boardings[index],
+            alightings: alightings[index],
+            load: loads[index],
+        }).collect(),
+        raw_boardings: boarding_total as u64,
+        raw_alightings: alighting_total as u64,
+        total,
+    }
+}
+
+// Verteilt die Differenz zwischen `target` und der Summe der Zählwerte auf die Haltestellen im Verhältnis
+// Zählwert / Zuverlässigkeit; ohne Zählwerte gleichmäßig auf die zulässigen Haltestellen. Fällt ein Wert unter 0,
+// bleibt er bei 0 und der Rest wird auf die übrigen Haltestellen verteilt.
+fn distribute(counts: &[f64], reliabilities: &[f64], target: f64, allowed: impl Fn(usize) -> bool) -> Vec<f64> {
+    let mut weights: Vec<f64> = counts.iter().zip(reliabilities).map(|(count, reliability)| count / reliability.max(f64::EPSILON)).collect();
+    if weights.iter().sum::<f64>() <= 0.0 {
+        weights = (0..counts.len()).map(|index| if allowed(index) { 1.0 } else { 0.0 }).collect();
+    }
+    let mut values = counts.to_vec();
+    for _ in 0..counts.len() {
+        let remaining = target - values.iter().sum::<f64>();
+        let weight_total: f64 = weights.iter().sum();
+        if remaining.abs() < 1e-9 || weight_total <= 0.0 {
+            break;
+        }
+        for (value, weight) in values.iter_mut().zip(weights.iter_mut()) {
+            *value += remaining * *weight / weight_total;
+            if *value < 0.0 {
+                *value = 0.0;
+                *weight = 0.0;
+            }
+        }
+    }
+    values
+}
+
+// Gleicht alle zugeordneten Fahrten aus
+pub fn balance_trips(trips: &MatchedTrips, method: BalancingMethod, params: &MatchingParams) -> Vec<BalancedTrip> {
+    trips.iter().map(|(trip_key, stops)| balance_trip(trip_key, stops, method, params)).collect()
+}
+
+// Schreibt Roh- und ausgeglichene Werte je Haltestelle nach {algorithmus}_ausgleich.csv im Ordner `output_folder`
+pub fn write_balanced_trips(algorithm: &str, trips: &[BalancedTrip], output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let output_file = Path::new(output_folder).join(format!("{}_ausgleich.csv", algorithm));
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(output_file)?;
+    writer.write_record([
+        "FAHRT", "FAHRZEUG", "SCHEDULE_ID", "LFD_NR", "ORT_NR", "ORT_NAME", "EINSTEIGER_ROH", "AUSSTEIGER_ROH",
+        "ZUVERLAESSIGKEIT", "EINSTEIGER", "AUSSTEIGER", "BESETZUNG",
+    ])?;
+    for trip in trips {
+        for stop in &trip.stops {
+            writer.write_record([
+                trip.trip_key.clone(),
+                trip.vehicle.clone(),
+                stop.schedule_id.clone(),
+                stop.lfnr.to_string(),
+                stop.ort_nr.to_string(),
+                stop.ort_name.clone(),
+                stop.raw_boardings.to_string(),
+                stop.raw_alightings.to_string(),
+                format!("{:.3}", stop.reliability),
+                format!("{:.2}", stop.boardings),
+                format!("{:.2}", stop.alightings),
+                format!("{:.2}", stop.load),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// In-Memory-Pipeline: die Schritte 1 bis 5 ohne Zwischendateien je Fahrzeug und Fahrt
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use crate::config::Paths;
+use crate::filter_bus_in_csv_rohdaten;
+use crate::filter_lines_in_csv_schedule;
+use crate::line_section_split;
+use crate::model::{self, ScheduleStop, VehicleEvent};
+use crate::remove_duplicates_in_busdata;
+use crate::remove_last_duplicates_in_busdata;
+use crate::schema::InputOptions;
+
+// Aufbereitete Daten eines Tages, wie sie sonst in den Zwischenordnern liegen
+#[derive(Debug, Default)]
+pub struct PreparedData {
+    pub stops_by_line: BTreeMap<u32, Vec<ScheduleStop>>,  // Schritt 1: Fahrplan je Linie
+    pub vehicles: HashMap<String, Vec<VehicleEvent>>,     // Schritte 2-4: bereinigte Ereignisse je Fahrzeug
+    pub trips: HashMap<String, Vec<ScheduleStop>>,        // Schritt 5: Fahrten je Schlüssel
+}
+
+// Liest Fahrplan und Rohdaten je einmal ein und führt die Schritte 1 bis 5 im Speicher aus
+pub fn prepare(schedule_path: &str, rohdaten_path: &str, options: &InputOptions) -> Result<PreparedData, Box<dyn Error>> {
+    let stops = model::read_schedule_stops_with(schedule_path, options)?;
+    println!("Fahrplan eingelesen: {} Haltestellen", stops.len());
+    let events = model::read_vehicle_events_with(rohdaten_path, options)?;
+    println!("Rohdaten eingelesen: {} Ereignisse", events.len());
+
+    // Schritt 1: Fahrplan nach Linien
+    let stops_by_line = filter_lines_in_csv_schedule::group_stops_by_line(stops.clone());
+
+    // Schritte 2 bis 4: Ereignisse nach Fahrzeugen und ohne Duplikate
+    let vehicles = filter_bus_in_csv_rohdaten::group_events_by_vehicle(events)
+        .into_iter()
+        .map(|(vehicle, events)| {
+            let events = remove_duplicates_in_busdata::remove_duplicate_events(events);
+            let events = remove_last_duplicates_in_busdata::remove_events_before_payment(&events);
+            (vehicle, events)
+        })
+        .collect();
+
+    // Schritt 5: Fahrplan in einzelne Fahrten
+    let trips = line_section_split::split_into_trips(stops);
+
+    Ok(PreparedData { stops_by_line, vehicles, trips })
+}
+
+// Schreibt die Zwischenergebnisse wie die einzelnen Schritte in die Ordner (nur zur Fehlersuche)
+pub fn write_intermediate(data: &PreparedData, paths: &Paths) -> Result<(), Box<dyn Error>> {
+    if let Some(folder) = &paths.fahrplan_ordner {
+        filter_lines_in_csv_schedule::write_lines_to_folder(folder, &data.stops_by_line)?;
+    }
+    if let Some(folder) = &paths.busdaten_ordner {
+        filter_bus_in_csv_rohdaten::write_vehicles_to_folder(folder, &data.vehicles)?;
+    }
+    if let Some(folder) = &paths.fahrten_ordner {
+        line_section_split::write_trips_to_folder(folder, &data.trips)?;
+    }
+    Ok(())
+}
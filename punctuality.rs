@@ -0,0 +1,284 @@
+// Pünktlichkeit der zugeordneten Fahrten: Verspätung je Haltestelle (Ist-Zeit `unixzeit` des Ereignisses gegen die
+// Soll-Zeit ZEIT), Pünktlichkeitsquote je Linie, Haltestelle und Stunde und der Verlauf der Verspätung entlang jeder
+// Fahrt.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use csv::WriterBuilder;
+use serde::Deserialize;
+
+use crate::matcher::MatchedTrips;
+use crate::time_bands::TimeBands;
+
+// Grenzen für "pünktlich" ([punctuality] in der Konfiguration)
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct PunctualityConfig {
+    pub early_seconds: i64, // Höchstens so viele Sekunden zu früh gilt als pünktlich
+    pub late_seconds: i64,  // Höchstens so viele Sekunden zu spät gilt als pünktlich
+}
+
+impl Default for PunctualityConfig {
+    fn default() -> Self {
+        PunctualityConfig { early_seconds: 60, late_seconds: 300 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Punctuality {
+    Early,
+    OnTime,
+    Late,
+}
+
+impl Punctuality {
+    pub fn classify(delay_s: i64, config: &PunctualityConfig) -> Self {
+        if delay_s < -config.early_seconds {
+            Punctuality::Early
+        } else if delay_s > config.late_seconds {
+            Punctuality::Late
+        } else {
+            Punctuality::OnTime
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Punctuality::Early => "zu_frueh",
+            Punctuality::OnTime => "puenktlich",
+            Punctuality::Late => "verspaetet",
+        }
+    }
+}
+
+// Verspätung an einer zugeordneten Haltestelle
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopDelay {
+    pub trip_key: String,
+    pub vehicle: String,
+    pub line: u32,
+    pub richtung: u32,
+    pub schedule_id: String,
+    pub lfnr: u32,
+    pub ort_nr: u32,
+    pub ort_name: String,
+    pub hour: Option<u32>,       // Stunde der Soll-Zeit
+    pub scheduled: u64,          // Soll-Zeit (ZEIT)
+    pub actual: u64,             // Ist-Zeit des Ereignisses (`unixzeit`, gleiche Uhr wie ZEIT)
+    pub delay_s: i64,            // Positiv = verspätet
+    pub growth_s: Option<i64>,   // Änderung gegenüber der vorherigen zugeordneten Haltestelle der Fahrt
+    pub punctuality: Punctuality,
+}
+
+// Verlauf der Verspätung einer Fahrt
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripDelay {
+    pub trip_key: String,
+    pub vehicle: String,
+    pub line: u32,
+    pub richtung: u32,
+    pub stops: usize,
+    pub first_delay_s: i64,
+    pub last_delay_s: i64,
+    pub max_delay_s: i64,
+}
+
+impl TripDelay {
+    // Zuwachs der Verspätung von der ersten bis zur letzten zugeordneten Haltestelle
+    pub fn growth_s(&self) -> i64 {
+        self.last_delay_s - self.first_delay_s
+    }
+
+    // Mittlerer Zuwachs je Haltestellenabstand
+    pub fn growth_per_stop_s(&self) -> f64 {
+        if self.stops < 2 { 0.0 } else { self.growth_s() as f64 / (self.stops - 1) as f64 }
+    }
+}
+
+// Kennzahlen einer Gruppe von Haltestellenereignissen
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PunctualityRow {
+    pub dimension: &'static str, // linie, haltestelle oder stunde
+    pub key: String,
+    pub events: usize,
+    pub early: usize,
+    pub on_time: usize,
+    pub late: usize,
+    pub delay_sum_s: i64,
+    pub max_delay_s: i64,
+}
+
+impl PunctualityRow {
+    // Anteil der pünktlichen Ereignisse (On-Time-Performance)
+    pub fn on_time_share(&self) -> f64 {
+        if self.events == 0 { 0.0 } else { self.on_time as f64 / self.events as f64 }
+    }
+
+    pub fn mean_delay_s(&self) -> f64 {
+        if self.events == 0 { 0.0 } else { self.delay_sum_s as f64 / self.events as f64 }
+    }
+
+    fn add(&mut self, delay: &StopDelay) {
+        if self.events == 0 || delay.delay_s > self.max_delay_s {
+            self.max_delay_s = delay.delay_s;
+        }
+        self.events += 1;
+        self.delay_sum_s += delay.delay_s;
+        match delay.punctuality {
+            Punctuality::Early => self.early += 1,
+            Punctuality::OnTime => self.on_time += 1,
+            Punctuality::Late => self.late += 1,
+        }
+    }
+}
+
+// Verspätung je zugeordneter Haltestelle, nach Fahrt und LFD_NR; liefert außerdem die Zahl der Halte ohne Soll-Zeit
+// (ZEIT = 0), die nicht bewertet werden
+pub fn stop_delays(trips: &MatchedTrips, time_bands: &TimeBands, config: &PunctualityConfig) -> (Vec<StopDelay>, usize) {
+    let mut delays = Vec::new();
+    let mut skipped = 0;
+    for (trip_key, stops) in trips {
+        let mut previous: Option<i64> = None;
+        for (stop, event) in stops {
+            if stop.zeit == 0 {
+                skipped += 1;
+                continue;
+            }
+            let delay_s = event.unixzeit as i64 - stop.zeit as i64;
+            delays.push(StopDelay {
+                trip_key: trip_key.clone(),
+                vehicle: event.vehicle.clone(),
+                line: stop.line,
+                richtung: stop.richtung,
+                schedule_id: stop.schedule_id.clone(),
+                lfnr: stop.lfnr,
+                ort_nr: stop.ort_nr,
+                ort_name: stop.ort_name.clone(),
+                hour: time_bands.stop_minute(stop).map(|minute| minute / 60),
+                scheduled: stop.zeit,
+                actual: event.unixzeit,
+                delay_s,
+                growth_s: previous.map(|previous| delay_s - previous),
+                punctuality: Punctuality::classify(delay_s, config),
+            });
+            previous = Some(delay_s);
+        }
+    }
+    (delays, skipped)
+}
+
+// Verlauf der Verspätung je Fahrt aus den Verspätungen der Haltestellen (nach Fahrt sortiert)
+pub fn trip_delays(delays: &[StopDelay]) -> Vec<TripDelay> {
+    let mut trips: Vec<TripDelay> = Vec::new();
+    for delay in delays {
+        match trips.last_mut() {
+            Some(trip) if trip.trip_key == delay.trip_key => {
+                trip.stops += 1;
+                trip.last_delay_s = delay.delay_s;
+                trip.max_delay_s = trip.max_delay_s.max(delay.delay_s);
+            }
+            _ => trips.push(TripDelay {
+                trip_key: delay.trip_key.clone(),
+                vehicle: delay.vehicle.clone(),
+                line: delay.line,
+                richtung: delay.richtung,
+                stops: 1,
+                first_delay_s: delay.delay_s,
+                last_delay_s: delay.delay_s,
+                max_delay_s: delay.delay_s,
+            }),
+        }
+    }
+    trips
+}
+
+// Pünktlichkeit je Linie, Haltestelle (ORT_NR) und Stunde
+pub fn punctuality_rows(delays: &[StopDelay]) -> Vec<PunctualityRow> {
+    let mut by_line: BTreeMap<u32, PunctualityRow> = BTreeMap::new();
+    let mut by_stop: BTreeMap<u32, PunctualityRow> = BTreeMap::new();
+    let mut by_hour: BTreeMap<Option<u32>, PunctualityRow> = BTreeMap::new();
+    for delay in delays {
+        by_line.entry(delay.line).or_insert_with(|| PunctualityRow { dimension: "linie", key: delay.line.to_string(), ..Default::default() }).add(delay);
+        by_stop.entry(delay.ort_nr).or_insert_with(|| PunctualityRow { dimension: "haltestelle", key: delay.ort_nr.to_string(), ..Default::default() }).add(delay);
+        by_hour.entry(delay.hour).or_insert_with(|| {
+            let key = delay.hour.map_or_else(|| "unbekannt".to_string(), |hour| format!("{:02}", hour));
+            PunctualityRow { dimension: "stunde", key, ..Default::default() }
+        }).add(delay);
+    }
+    by_line.into_values().chain(by_stop.into_values()).chain(by_hour.into_values()).collect()
+}
+
+// Schreibt {algorithmus}_verspaetung.csv (je Haltestelle), {algorithmus}_verspaetung_fahrten.csv (je Fahrt) und
+// {algorithmus}_puenktlichkeit.csv (je Linie, Haltestelle und Stunde) in den Ordner `output_folder`
+pub fn write_punctuality(algorithm: &str, delays: &[StopDelay], trips: &[TripDelay], rows: &[PunctualityRow], output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let folder = Path::new(output_folder);
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_verspaetung.csv", algorithm)))?;
+    writer.write_record([
+        "FAHRT", "FAHRZEUG", "LINIE", "RICHTUNG", "SCHEDULE_ID", "LFD_NR", "ORT_NR", "ORT_NAME", "STUNDE", "SOLL_ZEIT",
+        "IST_ZEIT", "VERSPAETUNG_S", "ZUWACHS_S", "KATEGORIE",
+    ])?;
+    for delay in delays {
+        writer.write_record([
+            delay.trip_key.clone(),
+            delay.vehicle.clone(),
+            delay.line.to_string(),
+            delay.richtung.to_string(),
+            delay.schedule_id.clone(),
+            delay.lfnr.to_string(),
+            delay.ort_nr.to_string(),
+            delay.ort_name.clone(),
+            delay.hour.map(|hour| hour.to_string()).unwrap_or_default(),
+            delay.scheduled.to_string(),
+            delay.actual.to_string(),
+            delay.delay_s.to_string(),
+            delay.growth_s.map(|growth| growth.to_string()).unwrap_or_default(),
+            delay.punctuality.label().to_string(),
+        ])?;
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_verspaetung_fahrten.csv", algorithm)))?;
+    writer.write_record([
+        "FAHRT", "FAHRZEUG", "LINIE", "RICHTUNG", "HALTESTELLEN", "VERSPAETUNG_ANFANG_S", "VERSPAETUNG_ENDE_S",
+        "MAX_VERSPAETUNG_S", "ZUWACHS_S", "ZUWACHS_JE_HALTESTELLE_S",
+    ])?;
+    for trip in trips {
+        writer.write_record([
+            trip.trip_key.clone(),
+            trip.vehicle.clone(),
+            trip.line.to_string(),
+            trip.richtung.to_string(),
+            trip.stops.to_string(),
+            trip.first_delay_s.to_string(),
+            trip.last_delay_s.to_string(),
+            trip.max_delay_s.to_string(),
+            trip.growth_s().to_string(),
+            format!("{:.1}", trip.growth_per_stop_s()),
+        ])?;
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_puenktlichkeit.csv", algorithm)))?;
+    writer.write_record([
+        "MERKMAL", "WERT", "EREIGNISSE", "ZU_FRUEH", "PUENKTLICH", "VERSPAETET", "ANTEIL_PUENKTLICH",
+        "MITTLERE_VERSPAETUNG_S", "MAX_VERSPAETUNG_S",
+    ])?;
+    for row in rows {
+        writer.write_record([
+            row.dimension.to_string(),
+            row.key.clone(),
+            row.events.to_string(),
+            row.early.to_string(),
+            row.on_time.to_string(),
+            row.late.to_string(),
+            format!("{:.4}", row.on_time_share()),
+            format!("{:.1}", row.mean_delay_s()),
+            row.max_delay_s.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
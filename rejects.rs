@@ -0,0 +1,108 @@
+// Fehlerbehandlung auf Zeilenebene: fehlerhafte Zeilen abbrechen lassen oder in eine Rejects-Datei schreiben
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+use csv::WriterBuilder;
+
+// Fehler beim Einlesen einer einzelnen Zeile
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub file: String,
+    pub row: u64,       // Zeilennummer in der Datei (Kopfzeile = 1)
+    pub column: String, // Spaltenname, leer wenn die ganze Zeile fehlerhaft ist
+    pub value: String,  // Ungeparster Wert aus der Datei
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.column.is_empty() {
+            write!(f, "Datei {}, Zeile {}: {}", self.file, self.row, self.message)
+        } else {
+            write!(f, "Datei {}, Zeile {}, Spalte '{}': Wert '{}' ungültig ({})",
+                self.file, self.row, self.column, self.value, self.message)
+        }
+    }
+}
+
+impl Error for RowError {}
+
+// Verhalten bei fehlerhaften Zeilen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    #[default]
+    Strict,  // Abbruch beim ersten Fehler
+    Lenient, // Zeile verwerfen, protokollieren und weiterlesen
+}
+
+// Eine verworfene Zeile mit Grund und Originalinhalt
+#[derive(Debug, Clone)]
+pub struct RejectedRow {
+    pub error: RowError,
+    pub raw: String,
+}
+
+// Sammlung aller verworfenen Zeilen eines Laufs
+#[derive(Debug, Default)]
+pub struct RejectLog {
+    rows: Mutex<BTreeMap<(String, u64), RejectedRow>>, // Schlüssel: (Datei, Zeile)
+}
+
+impl RejectLog {
+    // Protokolliert eine verworfene Zeile; wird dieselbe Datei mehrfach gelesen, zählt die Zeile nur einmal
+    pub fn record(&self, error: RowError, raw: String) {
+        let key = (error.file.clone(), error.row);
+        self.rows.lock().unwrap().entry(key).or_insert(RejectedRow { error, raw });
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Anzahl der verworfenen Zeilen je Datei
+    pub fn counts_per_file(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for rejected in self.rows.lock().unwrap().values() {
+            *counts.entry(rejected.error.file.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    // Schreibt alle verworfenen Zeilen mit Grund in eine CSV-Datei
+    pub fn write_csv<P: AsRef<Path>>(&self, file_path: P) -> Result<(), Box<dyn Error>> {
+        let mut csv_writer = WriterBuilder::new().delimiter(b';').from_path(file_path)?;
+        csv_writer.write_record(["DATEI", "ZEILE", "SPALTE", "WERT", "GRUND", "ZEILENINHALT"])?;
+        for rejected in self.rows.lock().unwrap().values() {
+            let error = &rejected.error;
+            csv_writer.write_record([
+                error.file.as_str(),
+                error.row.to_string().as_str(),
+                error.column.as_str(),
+                error.value.as_str(),
+                error.message.as_str(),
+                rejected.raw.as_str(),
+            ])?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    // Gibt die Anzahl der verworfenen Zeilen je Datei aus
+    pub fn print_summary(&self) {
+        let counts = self.counts_per_file();
+        if counts.is_empty() {
+            println!("Keine fehlerhaften Zeilen verworfen.");
+            return;
+        }
+        println!("Verworfene Zeilen je Datei:");
+        for (file, count) in counts {
+            println!("  {}: {}", file, count);
+        }
+    }
+}
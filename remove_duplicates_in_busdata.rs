@@ -1,51 +1,42 @@
 use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::{self, BufRead, Write};
-use std::path::Path;
 
-pub fn remove_duplicates_except_payment(input_file_path: &str, output_file_path: &str) -> Result<(), Box<dyn Error>> {
-    let file = File::open(input_file_path)?;
-    let reader = io::BufReader::new(file);
+use crate::model::{self, VehicleEvent};
+use crate::schema::InputOptions;
 
-    // Vector zum Speichern der Zeilen
-    let mut lines: Vec<String> = Vec::new();
-    let mut header = String::new();
+pub fn remove_duplicates_except_payment(input_file_path: &str, output_file_path: &str, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    let events = model::read_vehicle_events_with(input_file_path, input_options)?;
+    let kept = remove_duplicate_events(events);
 
-    // Iteriere über jede Zeile in der CSV-Datei
-    let mut previous_wkt = String::new();
-    for (index, line) in reader.lines().enumerate() {
-        let line_content = line?;
-        if index == 0 {
-            header = line_content.clone(); // Speichere die Überschrift
-            continue; // Überspringe die erste Zeile
-        }
-        let columns: Vec<&str> = line_content.split(';').collect();
-        let typ = columns[9]; // Index der Typ-Spalte (beginnt bei 0)
-        let wkt = columns[12]; // Index der WKT-Spalte (beginnt bei 0)
+    // Schreibe die bereinigten Ereignisse in die Ausgabedatei
+    model::write_vehicle_events(output_file_path, &kept)?;
+
+    Ok(())
+}
 
+// Entfernt aufeinanderfolgende Ereignisse am gleichen Ort (WKT), außer Zahlungen
+pub fn remove_duplicate_events(events: Vec<VehicleEvent>) -> Vec<VehicleEvent> {
+    // Vector zum Speichern der Ereignisse
+    let mut kept: Vec<VehicleEvent> = Vec::new();
+
+    // Iteriere über jedes Ereignis der CSV-Datei
+    let mut previous_wkt = String::new();
+    for event in events {
         // Überprüfe, ob der WKT-Wert in dieser Zeile mit dem vorherigen übereinstimmt
         // und ob der Typ nicht ZAHLUNG ist
-        if wkt == previous_wkt && typ != "ZAHLUNG" {
+        if event.wkt == previous_wkt && event.typ != "ZAHLUNG" {
             continue; // Springe zur nächsten Iteration, um die Zeile zu überspringen
         }
 
-        // Füge die Zeile hinzu
-        lines.push(line_content.clone());
-        previous_wkt = wkt.to_string(); // Aktualisiere den vorherigen WKT-Wert
+        previous_wkt = event.wkt.clone(); // Aktualisiere den vorherigen WKT-Wert
+        // Füge das Ereignis hinzu
+        kept.push(event);
     }
 
-    // Schreibe die bereinigten Zeilen in die Ausgabedatei
-    let mut output_file = File::create(output_file_path)?;
-    writeln!(output_file, "{}", header)?;
-    for line in &lines {
-        writeln!(output_file, "{}", line)?;
-    }
-
-    Ok(())
+    kept
 }
 
-pub fn process_csv_files_in_folder(folder_path: &str) -> Result<(), Box<dyn Error>> {
+pub fn process_csv_files_in_folder(folder_path: &str, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
     // Liste alle Dateien im Ordner auf
     let paths = fs::read_dir(folder_path)?;
 
@@ -65,7 +56,7 @@ pub fn process_csv_files_in_folder(folder_path: &str) -> Result<(), Box<dyn Erro
                     let output_file_path = format!("{}/{}", folder_path, file_name_str);
 
                     // Entferne Duplikate außer für Zahlungen
-                    remove_duplicates_except_payment(&file_path.to_string_lossy(), &output_file_path)?;
+                    remove_duplicates_except_payment(&file_path.to_string_lossy(), &output_file_path, input_options)?;
                     
                     println!("Duplicates removed for file: {}", file_name_str);
                 }
@@ -1,10 +1,11 @@
 use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
 use std::path::Path;
 
-pub fn remove_last_duplicates_in_busdata(directory: &str) -> Result<(), Box<dyn Error>> {
+use crate::model::{self, VehicleEvent};
+use crate::schema::InputOptions;
+
+pub fn remove_last_duplicates_in_busdata(directory: &str, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
     // Durchlaufen aller Dateien im Verzeichnis
     for entry in fs::read_dir(directory)? {
         let entry = entry?;
@@ -13,7 +14,7 @@ pub fn remove_last_duplicates_in_busdata(directory: &str) -> Result<(), Box<dyn
         // Überprüfen, ob es sich um eine CSV-Datei handelt
         if let Some(extension) = path.extension() {
             if extension == "csv" {
-                process_csv_file(&path)?;
+                process_csv_file(&path, input_options)?;
             }
         }
     }
@@ -38,48 +39,35 @@ pub fn remove_last_duplicates_in_busdata(directory: &str) -> Result<(), Box<dyn
 
 
 
-pub fn process_csv_file(file_path: &Path) -> Result<(), Box<dyn Error>> {
-    // Öffnen der Eingabedatei
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
+pub fn process_csv_file(file_path: &Path, input_options: &InputOptions) -> Result<(), Box<dyn Error>> {
+    // Einlesen der Eingabedatei
+    let events = model::read_vehicle_events_with(file_path, input_options)?;
 
     // Erstellen der Ausgabedatei im gleichen Verzeichnis mit "_filtered" im Dateinamen
     let output_file_path = file_path.with_extension("csv_filtered.csv");
-    let mut output_file = File::create(output_file_path)?;
-
-    // Durchlaufen der Zeilen der Eingabedatei
-    let mut prev_line: Option<Vec<String>> = None;
-    let mut next_line: Option<Vec<String>> = None;
+    let kept = remove_events_before_payment(&events);
 
-    for line in reader.lines() {
-        let current_line = line?;
-        let current_fields: Vec<String> = current_line.split(';').map(|s| s.to_string()).collect();
+    // Schreiben der verbleibenden Ereignisse in die Ausgabedatei
+    model::write_vehicle_events(&output_file_path, &kept)?;
 
-        // Speichern der aktuellen Zeile für den nächsten Durchlauf
-        next_line = Some(current_fields.clone());
+    Ok(())
+}
 
-        // Überprüfen, ob die vorherige Zeile existiert und die Bedingung erfüllt ist
-        if let Some(prev_fields) = prev_line.take() {
-            if let Some(next_fields) = next_line.clone() {
-                if next_fields[12] == current_fields[12] && next_fields[9] == "ZAHLUNG" {
-                    // Überspringen der aktuellen Zeile, da die nächste Zeile den Typ "ZAHLUNG" hat
-                    prev_line = next_line;
-                    continue;
-                }
+// Entfernt Ereignisse, auf die am gleichen Ort (WKT) direkt eine Zahlung folgt
+pub fn remove_events_before_payment(events: &[VehicleEvent]) -> Vec<VehicleEvent> {
+    // Durchlaufen der Ereignisse der Eingabedatei
+    let mut kept: Vec<VehicleEvent> = Vec::new();
+    for (index, event) in events.iter().enumerate() {
+        // Überprüfen, ob das nächste Ereignis am gleichen Ort stattfindet und den Typ "ZAHLUNG" hat
+        if let Some(next_event) = events.get(index + 1) {
+            if next_event.wkt == event.wkt && next_event.typ == "ZAHLUNG" {
+                // Überspringen des aktuellen Ereignisses, da das nächste den Typ "ZAHLUNG" hat
+                continue;
             }
-
-            // Schreiben der vorherigen Zeile in die Ausgabedatei
-            writeln!(&mut output_file, "{}", prev_fields.join(";"))?;
         }
 
-        // Speichern der aktuellen Zeile für den nächsten Durchlauf
-        prev_line = next_line;
+        kept.push(event.clone());
     }
 
-    // Überprüfen, ob eine vorherige Zeile übrig geblieben ist und sie in die Ausgabedatei schreiben
-    if let Some(prev_fields) = prev_line {
-        writeln!(&mut output_file, "{}", prev_fields.join(";"))?;
-    }
-
-    Ok(())
-}
\ No newline at end of file
+    kept
+}
@@ -0,0 +1,90 @@
+// Fahrgastzahlen je Haltestelle und je Linie/Richtung, jeweils nach Zeitband (Soll-Zeit ZEIT): Einsteiger und
+// Aussteiger roh und ausgeglichen sowie die Zahl der Fahrten. Ausgabe als Tabellen mit einer Zeile je Gruppe.
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use csv::WriterBuilder;
+
+use crate::matcher::MatchedTrips;
+use crate::passenger_balancing::BalancedTrip;
+use crate::time_bands::TimeBands;
+
+// Summen einer Gruppe
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ridership {
+    pub trips: BTreeSet<String>, // Fahrten mit einer zugeordneten Haltestelle in der Gruppe
+    pub boardings: u64,
+    pub alightings: u64,
+    pub balanced_boardings: f64,
+    pub balanced_alightings: f64,
+}
+
+impl Ridership {
+    fn add(&mut self, trip_key: &str, boardings: u32, alightings: u32, balanced: Option<(f64, f64)>) {
+        self.trips.insert(trip_key.to_string());
+        self.boardings += boardings as u64;
+        self.alightings += alightings as u64;
+        if let Some((balanced_boardings, balanced_alightings)) = balanced {
+            self.balanced_boardings += balanced_boardings;
+            self.balanced_alightings += balanced_alightings;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RidershipTables {
+    pub by_stop: BTreeMap<(u32, String), (String, Ridership)>,  // (ORT_NR, Zeitband) -> (ORT_NAME, Summen)
+    pub by_line: BTreeMap<(u32, u32, String), Ridership>,       // (Linie, Richtung, Zeitband) -> Summen
+}
+
+// Summiert die zugeordneten Haltestellen aller Fahrten; `balanced` liefert die ausgeglichenen Werte je Fahrt
+pub fn aggregate(trips: &MatchedTrips, balanced: &[BalancedTrip], time_bands: &TimeBands) -> RidershipTables {
+    let balanced_by_trip: BTreeMap<&str, &BalancedTrip> = balanced.iter().map(|trip| (trip.trip_key.as_str(), trip)).collect();
+    let mut tables = RidershipTables::default();
+    for (trip_key, stops) in trips {
+        let balanced_trip = balanced_by_trip.get(trip_key.as_str());
+        for (index, (stop, event)) in stops.iter().enumerate() {
+            let balanced_stop = balanced_trip
+                .and_then(|trip| trip.stops.get(index))
+                .map(|balanced_stop| (balanced_stop.boardings, balanced_stop.alightings));
+            let time_band = time_bands.stop_label(stop);
+            tables.by_stop.entry((stop.ort_nr, time_band.clone()))
+                .or_insert_with(|| (stop.ort_name.clone(), Ridership::default()))
+                .1.add(trip_key, event.einsteiger, event.aussteiger, balanced_stop);
+            tables.by_line.entry((stop.line, stop.richtung, time_band))
+                .or_default()
+                .add(trip_key, event.einsteiger, event.aussteiger, balanced_stop);
+        }
+    }
+    tables
+}
+
+// Schreibt {algorithmus}_fahrgaeste_haltestellen.csv und {algorithmus}_fahrgaeste_linien.csv in den Ordner `output_folder`
+pub fn write_ridership(algorithm: &str, tables: &RidershipTables, output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let folder = Path::new(output_folder);
+    let values = |ridership: &Ridership| [
+        ridership.trips.len().to_string(),
+        ridership.boardings.to_string(),
+        ridership.alightings.to_string(),
+        format!("{:.2}", ridership.balanced_boardings),
+        format!("{:.2}", ridership.balanced_alightings),
+    ];
+    let value_columns = ["FAHRTEN", "EINSTEIGER", "AUSSTEIGER", "EINSTEIGER_AUSGEGLICHEN", "AUSSTEIGER_AUSGEGLICHEN"];
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_fahrgaeste_haltestellen.csv", algorithm)))?;
+    writer.write_record(["ORT_NR", "ORT_NAME", "ZEITBAND"].iter().chain(value_columns.iter()))?;
+    for ((ort_nr, time_band), (ort_name, ridership)) in &tables.by_stop {
+        writer.write_record([ort_nr.to_string(), ort_name.clone(), time_band.clone()].into_iter().chain(values(ridership)))?;
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_fahrgaeste_linien.csv", algorithm)))?;
+    writer.write_record(["LINIE", "RICHTUNG", "ZEITBAND"].iter().chain(value_columns.iter()))?;
+    for ((line, richtung, time_band), ridership) in &tables.by_line {
+        writer.write_record([line.to_string(), richtung.to_string(), time_band.clone()].into_iter().chain(values(ridership)))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
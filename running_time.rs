@@ -0,0 +1,211 @@
+// Fahrzeiten zwischen aufeinanderfolgenden zugeordneten Haltestellen: Ist-Fahrzeit von der Abfahrt an Haltestelle n
+// (`zeit_next` des Ereignisses, ohne `zeit_next` dessen `zeit`) bis zur Ankunft an Haltestelle n+1 (`zeit`) gegen die
+// Soll-Fahrzeit aus den Soll-Zeiten ZEIT. Je Abschnitt und Zeitband werden Perzentile der Ist-Fahrzeit ausgegeben.
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use csv::WriterBuilder;
+use serde::Deserialize;
+
+use crate::matcher::MatchedTrips;
+use crate::model::ScheduleStop;
+use crate::time_bands::TimeBands;
+
+// Einstellungen der Fahrzeitauswertung ([running_times] in der Konfiguration)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RunningTimeConfig {
+    pub percentiles: Vec<f64>, // Auszugebende Perzentile der Ist-Fahrzeit (0 bis 100)
+}
+
+impl Default for RunningTimeConfig {
+    fn default() -> Self {
+        RunningTimeConfig { percentiles: vec![10.0, 50.0, 85.0, 95.0] }
+    }
+}
+
+// Fahrzeit einer Fahrt auf einem Abschnitt
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentRun {
+    pub trip_key: String,
+    pub vehicle: String,
+    pub line: u32,
+    pub richtung: u32,
+    pub from: (u32, String), // ORT_NR und ORT_NAME der Abfahrtshaltestelle
+    pub to: (u32, String),   // ORT_NR und ORT_NAME der Ankunftshaltestelle
+    pub time_band: String,   // Zeitband der Soll-Zeit an der Abfahrtshaltestelle
+    pub scheduled_s: i64,
+    pub actual_s: i64,
+}
+
+impl SegmentRun {
+    // Positiv = länger als geplant
+    pub fn difference_s(&self) -> i64 {
+        self.actual_s - self.scheduled_s
+    }
+}
+
+// Fahrzeiten eines Abschnitts in einem Zeitband
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentStats {
+    pub from_name: String,
+    pub to_name: String,
+    pub runs: usize,
+    pub mean_scheduled_s: f64,
+    pub mean_actual_s: f64,
+    pub slower_share: f64,           // Anteil der Fahrten, die länger als geplant brauchten
+    pub percentiles_s: Vec<f64>,     // In der Reihenfolge von RunningTimeConfig::percentiles
+}
+
+// Schlüssel eines Abschnitts: (Linie, Richtung, von ORT_NR, nach ORT_NR, Zeitband)
+pub type SegmentKey = (u32, u32, u32, u32, String);
+
+// Übersprungene Paare zugeordneter Haltestellen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SkippedSegments {
+    pub not_adjacent: usize, // LFD_NR nicht aufeinanderfolgend (Haltestelle dazwischen ohne Zuordnung)
+    pub negative: usize,     // Negative Ist-Fahrzeit
+    pub no_schedule: usize,  // Soll-Fahrzeit fehlt (ZEIT = 0) oder ist negativ
+}
+
+// Fahrzeiten aller Abschnitte zwischen aufeinanderfolgenden zugeordneten Haltestellen; Paare, deren LFD_NR nicht
+// aufeinander folgen, und Abschnitte ohne gültige Soll- oder Ist-Fahrzeit werden nicht ausgewertet, sondern gezählt
+pub fn segment_runs(trips: &MatchedTrips, time_bands: &TimeBands) -> (Vec<SegmentRun>, SkippedSegments) {
+    let mut runs = Vec::new();
+    let mut skipped = SkippedSegments::default();
+    for (trip_key, stops) in trips {
+        for pair in stops.windows(2) {
+            let (from_stop, from_event) = &pair[0];
+            let (to_stop, to_event) = &pair[1];
+            if to_stop.lfnr != from_stop.lfnr + 1 {
+                skipped.not_adjacent += 1;
+                continue;
+            }
+            let Some(scheduled_s) = scheduled_seconds(from_stop, to_stop) else {
+                skipped.no_schedule += 1;
+                continue;
+            };
+            let departure = from_event.zeit_next.unwrap_or(from_event.zeit);
+            let actual_s = to_event.zeit.seconds() - departure.seconds();
+            if actual_s < 0 {
+                skipped.negative += 1;
+                continue;
+            }
+            runs.push(SegmentRun {
+                trip_key: trip_key.clone(),
+                vehicle: from_event.vehicle.clone(),
+                line: from_stop.line,
+                richtung: from_stop.richtung,
+                from: (from_stop.ort_nr, from_stop.ort_name.clone()),
+                to: (to_stop.ort_nr, to_stop.ort_name.clone()),
+                time_band: time_bands.stop_label(from_stop),
+                scheduled_s,
+                actual_s,
+            });
+        }
+    }
+    (runs, skipped)
+}
+
+// Soll-Fahrzeit von der Abfahrt an `from` bis zur Ankunft an `to`: ZEIT ist die Soll-Ankunft, die Soll-Abfahrt liegt
+// um die Haltezeit ABFAHRT - ANKUNFT später. None ohne ZEIT oder bei negativer Soll-Fahrzeit.
+fn scheduled_seconds(from: &ScheduleStop, to: &ScheduleStop) -> Option<i64> {
+    if from.zeit == 0 || to.zeit == 0 {
+        return None;
+    }
+    let departure = from.zeit as i64 + from.abfahrt.saturating_sub(from.ankunft) as i64;
+    let scheduled_s = to.zeit as i64 - departure;
+    (scheduled_s >= 0).then_some(scheduled_s)
+}
+
+// Perzentil mit linearer Interpolation zwischen den Rängen; `sorted` muss aufsteigend sortiert und nicht leer sein
+pub fn percentile(sorted: &[i64], percent: f64) -> f64 {
+    let rank = percent / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] as f64 + (sorted[upper] - sorted[lower]) as f64 * (rank - lower as f64)
+}
+
+// Kennzahlen je Abschnitt und Zeitband
+pub fn aggregate(runs: &[SegmentRun], config: &RunningTimeConfig) -> Result<BTreeMap<SegmentKey, SegmentStats>, Box<dyn Error>> {
+    if let Some(invalid) = config.percentiles.iter().find(|percent| !(0.0..=100.0).contains(*percent)) {
+        return Err(format!("Fahrzeiten: Perzentil {} liegt nicht zwischen 0 und 100", invalid).into());
+    }
+    let mut groups: BTreeMap<SegmentKey, Vec<&SegmentRun>> = BTreeMap::new();
+    for run in runs {
+        groups.entry((run.line, run.richtung, run.from.0, run.to.0, run.time_band.clone())).or_default().push(run);
+    }
+    Ok(groups.into_iter().map(|(key, group)| {
+        let count = group.len() as f64;
+        let mut actual: Vec<i64> = group.iter().map(|run| run.actual_s).collect();
+        actual.sort_unstable();
+        let stats = SegmentStats {
+            from_name: group[0].from.1.clone(),
+            to_name: group[0].to.1.clone(),
+            runs: group.len(),
+            mean_scheduled_s: group.iter().map(|run| run.scheduled_s).sum::<i64>() as f64 / count,
+            mean_actual_s: actual.iter().sum::<i64>() as f64 / count,
+            slower_share: group.iter().filter(|run| run.difference_s() > 0).count() as f64 / count,
+            percentiles_s: config.percentiles.iter().map(|percent| percentile(&actual, *percent)).collect(),
+        };
+        (key, stats)
+    }).collect())
+}
+
+// Schreibt {algorithmus}_fahrzeiten.csv (je Fahrt und Abschnitt) und {algorithmus}_fahrzeiten_abschnitte.csv (je
+// Abschnitt und Zeitband, eine Spalte P<n>_S je Perzentil) in den Ordner `output_folder`
+pub fn write_running_times(algorithm: &str, runs: &[SegmentRun], stats: &BTreeMap<SegmentKey, SegmentStats>, config: &RunningTimeConfig, output_folder: &str) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(output_folder)?;
+    let folder = Path::new(output_folder);
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_fahrzeiten.csv", algorithm)))?;
+    writer.write_record([
+        "FAHRT", "FAHRZEUG", "LINIE", "RICHTUNG", "VON_ORT_NR", "VON_ORT_NAME", "NACH_ORT_NR", "NACH_ORT_NAME", "ZEITBAND",
+        "SOLL_FAHRZEIT_S", "IST_FAHRZEIT_S", "ABWEICHUNG_S",
+    ])?;
+    for run in runs {
+        writer.write_record([
+            run.trip_key.clone(),
+            run.vehicle.clone(),
+            run.line.to_string(),
+            run.richtung.to_string(),
+            run.from.0.to_string(),
+            run.from.1.clone(),
+            run.to.0.to_string(),
+            run.to.1.clone(),
+            run.time_band.clone(),
+            run.scheduled_s.to_string(),
+            run.actual_s.to_string(),
+            run.difference_s().to_string(),
+        ])?;
+    }
+    writer.flush()?;
+
+    let mut writer = WriterBuilder::new().delimiter(b';').from_path(folder.join(format!("{}_fahrzeiten_abschnitte.csv", algorithm)))?;
+    let mut header: Vec<String> = [
+        "LINIE", "RICHTUNG", "VON_ORT_NR", "VON_ORT_NAME", "NACH_ORT_NR", "NACH_ORT_NAME", "ZEITBAND", "FAHRTEN",
+        "SOLL_FAHRZEIT_S", "MITTLERE_IST_FAHRZEIT_S", "ANTEIL_LAENGER",
+    ].iter().map(|column| column.to_string()).collect();
+    header.extend(config.percentiles.iter().map(|percent| format!("P{}_S", percent)));
+    writer.write_record(&header)?;
+    for ((line, richtung, from_nr, to_nr, time_band), stats) in stats {
+        let mut record = vec![
+            line.to_string(),
+            richtung.to_string(),
+            from_nr.to_string(),
+            stats.from_name.clone(),
+            to_nr.to_string(),
+            stats.to_name.clone(),
+            time_band.clone(),
+            stats.runs.to_string(),
+            format!("{:.1}", stats.mean_scheduled_s),
+            format!("{:.1}", stats.mean_actual_s),
+            format!("{:.4}", stats.slower_share),
+        ];
+        record.extend(stats.percentiles_s.iter().map(|value| format!("{:.1}", value)));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,187 @@
+// Zuordnung der CSV-Spalten über die Namen in der Kopfzeile statt über feste Positionen
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use csv::{ReaderBuilder, StringRecord};
+use serde::de::DeserializeOwned;
+
+use crate::rejects::{ParseMode, RejectLog, RowError};
+
+// Spalten der Fahrplandaten in der Reihenfolge der Felder von ScheduleStop
+pub const SCHEDULE_COLUMNS: &[&str] = &[
+    "SCHEDULE_ID", "DATUM", "FRT_FID", "FRT_START", "LINE", "RICHTUNG", "VARIANTE", "UMLAUF",
+    "LFD_NR", "ANKUNFT", "ABFAHRT", "ZEITPKT", "ZEIT", "ORT_NR", "ORT_NAME", "LON", "LAT", "X", "Y",
+    "FAHRT_START", "FAHRT_ENDE", "WKT",
+];
+
+// Spalten der Fahrzeugdaten in der Reihenfolge der Felder von VehicleEvent
+pub const VEHICLE_COLUMNS: &[&str] = &[
+    "vehicle", "datum", "zeit", "zeit_next", "unixzeit", "lat", "lon", "x", "y", "typ",
+    "einsteiger", "aussteiger", "wkt",
+];
+
+// Fehler, wenn eine benötigte Spalte in einer Datei nicht vorhanden ist
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError {
+    pub file: String,
+    pub column: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Datei {}: benötigte Spalte '{}' nicht in der Kopfzeile gefunden", self.file, self.column)
+    }
+}
+
+impl Error for SchemaError {}
+
+// Alternative Spaltennamen einzelner Betreiber (Alias -> Spaltenname des Datenmodells)
+#[derive(Debug, Clone, Default)]
+pub struct ColumnAliases {
+    aliases: HashMap<String, String>,
+}
+
+impl ColumnAliases {
+    pub fn new() -> Self {
+        ColumnAliases::default()
+    }
+
+    // Fügt einen alternativen Namen für eine Spalte hinzu
+    pub fn insert(&mut self, alias: &str, column: &str) {
+        self.aliases.insert(normalize(alias), column.to_string());
+    }
+
+    // Liest die Aliase aus einer CSV-Datei mit den Spalten ALIAS;SPALTE
+    pub fn from_file<P: AsRef<Path>>(file_path: P) -> Result<Self, Box<dyn Error>> {
+        let mut csv_reader = ReaderBuilder::new().delimiter(b';').from_path(file_path)?;
+        let mut aliases = ColumnAliases::new();
+        for result in csv_reader.records() {
+            let record = result?;
+            if let (Some(alias), Some(column)) = (record.get(0), record.get(1)) {
+                aliases.insert(alias, column.trim());
+            }
+        }
+        Ok(aliases)
+    }
+
+    fn resolve(&self, header: &str) -> Option<&str> {
+        self.aliases.get(&normalize(header)).map(|column| column.as_str())
+    }
+}
+
+// Normalisiert einen Spaltennamen (Leerzeichen und BOM entfernen)
+fn normalize(name: &str) -> String {
+    name.trim().trim_start_matches('\u{feff}').to_string()
+}
+
+// Positionen der benötigten Spalten in einer konkreten Datei
+#[derive(Debug, Clone)]
+pub struct ColumnMap {
+    indices: Vec<usize>,
+}
+
+impl ColumnMap {
+    // Sucht jede benötigte Spalte in der Kopfzeile: zuerst exakt, dann über die Aliase,
+    // zuletzt ohne Beachtung der Groß-/Kleinschreibung (nur wenn eindeutig)
+    pub fn from_headers(file: &str, headers: &StringRecord, columns: &[&str], aliases: &ColumnAliases) -> Result<Self, SchemaError> {
+        let names: Vec<String> = headers.iter().map(normalize).collect();
+        let mut indices = Vec::with_capacity(columns.len());
+
+        for column in columns {
+            let exact = names.iter().position(|name| name == column);
+            let aliased = || names.iter().position(|name| aliases.resolve(name) == Some(*column));
+            let case_insensitive = || {
+                let candidates: Vec<usize> = names.iter().enumerate()
+                    .filter(|(_, name)| name.eq_ignore_ascii_case(column))
+                    .map(|(index, _)| index)
+                    .collect();
+                if candidates.len() == 1 { Some(candidates[0]) } else { None }
+            };
+
+            match exact.or_else(aliased).or_else(case_insensitive) {
+                Some(index) => indices.push(index),
+                None => return Err(SchemaError { file: file.to_string(), column: column.to_string() }),
+            }
+        }
+
+        Ok(ColumnMap { indices })
+    }
+
+    // Ordnet die Felder eines Datensatzes in die Reihenfolge der benötigten Spalten um
+    pub fn project(&self, record: &StringRecord) -> StringRecord {
+        self.indices.iter().map(|&index| record.get(index).unwrap_or("")).collect()
+    }
+}
+
+// Einstellungen für das Einlesen der Eingabedateien
+#[derive(Debug, Default)]
+pub struct InputOptions {
+    pub aliases: ColumnAliases,
+    pub mode: ParseMode,
+    pub rejects: RejectLog,
+}
+
+impl InputOptions {
+    // Im strikten Modus wird der Fehler zurückgegeben, sonst wird die Zeile verworfen und protokolliert
+    fn reject(&self, error: RowError, raw: String) -> Result<(), Box<dyn Error>> {
+        match self.mode {
+            ParseMode::Strict => Err(Box::new(error)),
+            ParseMode::Lenient => {
+                self.rejects.record(error, raw);
+                Ok(())
+            }
+        }
+    }
+}
+
+// Liest eine CSV-Datei und ordnet die Spalten anhand der Kopfzeile den Feldern von T zu
+pub fn read_mapped_records<T: DeserializeOwned>(file_path: &Path, columns: &[&str], options: &InputOptions) -> Result<Vec<T>, Box<dyn Error>> {
+    let file = file_path.display().to_string();
+    let mut csv_reader = ReaderBuilder::new()
+        .delimiter(b';')
+        .from_path(file_path)?;
+
+    let headers = csv_reader.headers()?.clone();
+    let column_map = ColumnMap::from_headers(&file, &headers, columns, &options.aliases)?;
+
+    let mut records = Vec::new();
+    for result in csv_reader.records() {
+        // Zeilen, die sich nicht als CSV lesen lassen (z.B. falsche Spaltenanzahl)
+        let record = match result {
+            Ok(record) => record,
+            Err(err) => {
+                if let csv::ErrorKind::Io(_) = err.kind() {
+                    return Err(err.into());
+                }
+                let row = err.position().map(|position| position.line()).unwrap_or(0);
+                let error = RowError { file: file.clone(), row, column: String::new(), value: String::new(), message: err.to_string() };
+                options.reject(error, String::new())?;
+                continue;
+            }
+        };
+
+        // Zeilen mit Werten, die nicht zum Datentyp der Spalte passen
+        let projected = column_map.project(&record);
+        match projected.deserialize(None) {
+            Ok(value) => records.push(value),
+            Err(err) => {
+                let row = record.position().map(|position| position.line()).unwrap_or(0);
+                let error = row_error(&file, row, columns, &projected, &err);
+                options.reject(error, record.iter().collect::<Vec<&str>>().join(";"))?;
+            }
+        }
+    }
+    Ok(records)
+}
+
+// Übersetzt einen Deserialisierungsfehler in einen RowError mit Spaltenname und Rohwert
+fn row_error(file: &str, row: u64, columns: &[&str], projected: &StringRecord, err: &csv::Error) -> RowError {
+    let (field, message) = match err.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => (err.field(), err.kind().to_string()),
+        _ => (None, err.to_string()),
+    };
+    let column = field.and_then(|index| columns.get(index as usize)).map(|column| column.to_string()).unwrap_or_default();
+    let value = field.and_then(|index| projected.get(index as usize)).unwrap_or("").to_string();
+    RowError { file: file.to_string(), row, column, value, message }
+}
@@ -0,0 +1,82 @@
+// Zeitbänder der Analysen: entweder feste Intervalle (z.B. 15 oder 60 Minuten) oder benannte Bänder wie die
+// Hauptverkehrszeiten. Maßgeblich ist die Soll-Zeit ZEIT der Haltestelle in Ortszeit (MEZ/MESZ oder
+// [input] utc_offset_minutes).
+use std::error::Error;
+use serde::Deserialize;
+
+use crate::matching_params::{local_minute, TimeBand};
+use crate::model::ScheduleStop;
+use crate::timestamp::TimeZone;
+
+// Ein benanntes Zeitband ([[time_bands.bands]])
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NamedBand {
+    pub name: String,
+    pub time_band: String, // "HH:MM-HH:MM", auch über Mitternacht
+}
+
+// Einstellungen der Zeitbänder ([time_bands] in der Konfiguration)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TimeBandConfig {
+    pub interval_minutes: u32,  // Länge der festen Intervalle, wenn keine benannten Bänder angegeben sind
+    pub bands: Vec<NamedBand>,  // Benannte Bänder; Zeiten außerhalb aller Bänder fallen unter `other`
+    pub other: String,
+}
+
+impl Default for TimeBandConfig {
+    fn default() -> Self {
+        TimeBandConfig { interval_minutes: 60, bands: Vec::new(), other: "sonstige".to_string() }
+    }
+}
+
+// Geprüfte Zeitbänder
+#[derive(Debug, Clone)]
+pub struct TimeBands {
+    interval_minutes: u32,
+    bands: Vec<(String, TimeBand)>,
+    other: String,
+    time_zone: TimeZone,
+}
+
+impl TimeBands {
+    pub fn new(config: &TimeBandConfig, time_zone: TimeZone) -> Result<Self, Box<dyn Error>> {
+        if config.interval_minutes == 0 || 24 * 60 % config.interval_minutes != 0 {
+            return Err(format!("Zeitbänder: interval_minutes = {} muss ein Teiler von 1440 sein", config.interval_minutes).into());
+        }
+        let bands = config.bands.iter().map(|band| {
+            TimeBand::parse(&band.time_band)
+                .map(|time_band| (band.name.clone(), time_band))
+                .ok_or_else(|| format!("Zeitband {}: '{}' ungültig, erwartet HH:MM-HH:MM", band.name, band.time_band).into())
+        }).collect::<Result<Vec<(String, TimeBand)>, Box<dyn Error>>>()?;
+        Ok(TimeBands { interval_minutes: config.interval_minutes, bands, other: config.other.clone(), time_zone })
+    }
+
+    // Bezeichnung des Zeitbands einer Minute nach Mitternacht: das erste passende benannte Band bzw. "HH:MM-HH:MM"
+    pub fn label(&self, minute: u32) -> String {
+        if !self.bands.is_empty() {
+            return self.bands.iter()
+                .find(|(_, band)| band.contains(minute))
+                .map_or_else(|| self.other.clone(), |(name, _)| name.clone());
+        }
+        let start = minute / self.interval_minutes * self.interval_minutes;
+        let end = (start + self.interval_minutes) % (24 * 60);
+        format!("{:02}:{:02}-{:02}:{:02}", start / 60, start % 60, end / 60, end % 60)
+    }
+
+    // Minute nach Mitternacht der Soll-Zeit einer Haltestelle in Ortszeit; None ohne Soll-Zeit (ZEIT = 0)
+    pub fn stop_minute(&self, stop: &ScheduleStop) -> Option<u32> {
+        local_minute(stop.zeit, self.time_zone)
+    }
+
+    // Zeitband einer Haltestelle (Soll-Zeit), "unbekannt" ohne Soll-Zeit
+    pub fn stop_label(&self, stop: &ScheduleStop) -> String {
+        self.stop_minute(stop).map_or_else(|| "unbekannt".to_string(), |minute| self.label(minute))
+    }
+
+    // Zeitband einer Fahrt (Soll-Zeit der ersten Haltestelle), "unbekannt" ohne Soll-Zeit
+    pub fn trip_label(&self, first: &ScheduleStop) -> String {
+        self.stop_label(first)
+    }
+}
@@ -0,0 +1,160 @@
+// Zeitstempel der Fahrzeugereignisse (`zeit`, `zeit_next`) in Sekunden seit 1970-01-01 00:00:00. Gelesen werden
+// Unix-Sekunden (mehr als 8 Ziffern), Datum mit Uhrzeit (YYYY-MM-DD, DD.MM.YYYY oder YYYYMMDD, getrennt durch Leerzeichen oder T) und eine
+// reine Uhrzeit, die mit dem Datum des Ereignisses ergänzt wird. Sekundenbruchteile und ein abschließendes Z werden
+// ignoriert, Angaben ohne Zeitzone werden nicht umgerechnet. Geschrieben wird "YYYY-MM-DD HH:MM:SS".
+use std::fmt;
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    // Liest eine Zeitangabe; `date` ist das Datum des Ereignisses für Angaben ohne Datum
+    pub fn parse(value: &str, date: &str) -> Option<Self> {
+        let value = value.trim().trim_end_matches('Z');
+        // Nur mehr als 8 Ziffern sind Unix-Sekunden, damit ein Datum YYYYMMDD nicht als Zeitpunkt 1970 gelesen wird
+        if value.len() > 8 && value.bytes().all(|byte| byte.is_ascii_digit()) {
+            return value.parse().ok().map(Timestamp);
+        }
+        let (day, time) = match value.find([' ', 'T']) {
+            Some(index) => (parse_date(&value[..index])?, &value[index + 1..]),
+            None => (parse_date(date.trim())?, value),
+        };
+        Some(Timestamp(day * 86_400 + parse_time(time.trim())?))
+    }
+
+    pub fn seconds(self) -> i64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.0.div_euclid(86_400));
+        let seconds = self.0.rem_euclid(86_400);
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+// Zeitzone der Tageszeiten aus Unix-Zeitstempeln: Europe/Berlin (MEZ, von Ende März bis Ende Oktober MESZ) oder ein
+// fester Abstand zu UTC in Minuten
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeZone {
+    EuropeBerlin,
+    Fixed(i32),
+}
+
+impl TimeZone {
+    // Abstand der Ortszeit zu UTC in Minuten zum Zeitpunkt `unix`
+    pub fn offset_minutes(self, unix: i64) -> i32 {
+        match self {
+            TimeZone::Fixed(offset) => offset,
+            TimeZone::EuropeBerlin => {
+                // Sommerzeit vom letzten Sonntag im März bis zum letzten Sonntag im Oktober, jeweils ab 01:00 UTC
+                let (year, _, _) = civil_from_days(unix.div_euclid(86_400));
+                let start = last_sunday(year, 3) * 86_400 + 3600;
+                let end = last_sunday(year, 10) * 86_400 + 3600;
+                if (start..end).contains(&unix) { 120 } else { 60 }
+            }
+        }
+    }
+}
+
+// Tage seit 1970-01-01 des letzten Sonntags eines Monats mit 31 Tagen (1970-01-01 war ein Donnerstag)
+fn last_sunday(year: i64, month: u32) -> i64 {
+    let last_day = days_from_civil(year, month, 31);
+    last_day - (last_day + 4).rem_euclid(7)
+}
+
+// Tage seit 1970-01-01 eines Datums "YYYY-MM-DD", "DD.MM.YYYY" oder "YYYYMMDD"
+fn parse_date(value: &str) -> Option<i64> {
+    let parts: Vec<&str> = if value.contains('-') {
+        value.split('-').collect()
+    } else if value.contains('.') {
+        value.split('.').rev().collect()
+    } else if value.len() == 8 && value.bytes().all(|byte| byte.is_ascii_digit()) {
+        vec![&value[..4], &value[4..6], &value[6..]]
+    } else {
+        return None;
+    };
+    if parts.len() != 3 {
+        return None;
+    }
+    let year: i64 = parts[0].parse().ok()?;
+    let month: u32 = parts[1].parse().ok()?;
+    let day: u32 = parts[2].parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+// Sekunden seit Mitternacht einer Uhrzeit "HH:MM" oder "HH:MM:SS[.fff]"
+fn parse_time(value: &str) -> Option<i64> {
+    let value = value.split('.').next()?;
+    let mut parts = value.split(':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let seconds: i64 = parts.next().map_or(Some(0), |seconds| seconds.parse().ok())?;
+    if parts.next().is_some() || !(0..24).contains(&hours) || !(0..60).contains(&minutes) || !(0..61).contains(&seconds) {
+        return None;
+    }
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+// Tage seit 1970-01-01 im gregorianischen Kalender (nach H. Hinnant, "chrono-Compatible Low-Level Date Algorithms")
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Umkehrung von `days_from_civil`: (Jahr, Monat, Tag)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn berlin_switches_to_summer_time_on_the_last_sundays_of_march_and_october() {
+        let berlin = TimeZone::EuropeBerlin;
+        // 2023: Sommerzeit vom 26.03. 01:00 UTC bis 29.10. 01:00 UTC
+        let start = Timestamp::parse("2023-03-26 01:00:00", "").unwrap().seconds();
+        let end = Timestamp::parse("2023-10-29 01:00:00", "").unwrap().seconds();
+        assert_eq!(berlin.offset_minutes(start - 1), 60);
+        assert_eq!(berlin.offset_minutes(start), 120);
+        assert_eq!(berlin.offset_minutes(1_698_213_600), 120); // 2023-10-25 06:00 UTC
+        assert_eq!(berlin.offset_minutes(end - 1), 120);
+        assert_eq!(berlin.offset_minutes(end), 60);
+        assert_eq!(TimeZone::Fixed(0).offset_minutes(start), 0);
+    }
+
+    #[test]
+    fn compact_dates_must_be_ascii_digits() {
+        assert_eq!(Timestamp::parse("12:00", "20231025"), Timestamp::parse("2023-10-25 12:00", ""));
+        assert_eq!(Timestamp::parse("12:00", "202ä102"), None);
+        assert_eq!(Timestamp::parse("12:00", "2023x025"), None);
+    }
+}
@@ -0,0 +1,49 @@
+// Bewertung eines Fahrzeugs über alle Haltestellen einer Fahrt, damit jede Fahrt genau einem Fahrzeug zugeordnet wird
+use std::cmp::Ordering;
+
+use crate::config::Tolerances;
+use crate::event_index::haversine_m;
+use crate::heuristic_cost::HeuristicCost;
+use crate::model::{ScheduleStop, VehicleEvent};
+
+// Zuordnung einer Fahrt zu einem Fahrzeug mit Bewertung (gemeinsames Ergebnis aller Algorithmen je Fahrt)
+#[derive(Debug, Clone)]
+pub struct TripMatch {
+    pub trip_key: String,
+    pub vehicle: String,
+    pub stops: Vec<(ScheduleStop, VehicleEvent)>, // Haltestelle -> Ereignis
+    pub score: TripScore,                         // Bewertung des Fahrzeugs über alle Haltestellen der Fahrt
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TripScore {
+    pub stops_total: usize,    // Haltestellen der Fahrt
+    pub stops_covered: usize,  // Haltestellen mit zugeordnetem Ereignis
+    pub spatial_error_m: f64,  // Summe der Entfernungen zwischen Haltestelle und Ereignis
+    pub temporal_error_s: u64, // Summe der Abweichungen zwischen Soll- und Ist-Zeit
+    pub cost: f64,             // Summe der Kosten der Paare nach der Kostenfunktion ([heuristic])
+}
+
+impl TripScore {
+    pub fn from_pairs(stops_total: usize, pairs: &[(ScheduleStop, VehicleEvent)], cost: &HeuristicCost, tolerances: &Tolerances) -> Self {
+        let mut score = TripScore { stops_total, stops_covered: pairs.len(), ..Default::default() };
+        for (stop, event) in pairs {
+            let distance_m = haversine_m(stop.lat, stop.lon, event.lat, event.lon);
+            let time_difference = (stop.zeit as i64 - event.unixzeit as i64).unsigned_abs();
+            score.spatial_error_m += distance_m;
+            score.temporal_error_s += time_difference;
+            score.cost += cost.pair_cost(distance_m, time_difference, tolerances);
+        }
+        score
+    }
+
+    // Mehr abgedeckte Haltestellen gehen vor, bei Gleichstand entscheiden die geringeren Abweichungen
+    pub fn compare(&self, other: &TripScore) -> Ordering {
+        self.stops_covered.cmp(&other.stops_covered).then(other.cost.total_cmp(&self.cost))
+    }
+}
+
+// Abweichung eines Paares in Ort und Zeit, jeweils bezogen auf die Toleranz
+pub fn normalized_error(distance_m: f64, time_difference_s: u64, tolerances: &Tolerances) -> f64 {
+    distance_m / tolerances.match_distance_m.max(1.0) + time_difference_s as f64 / tolerances.time_tolerance_seconds.max(1) as f64
+}